@@ -0,0 +1,214 @@
+//! Plays seeded matches between computer opponents, or a computer and a scripted player, as fast as the host
+//! can run them and reports how they went. Useful for tuning the opponents and the ball from data.
+//!
+//! Run with `cargo run --release --example simulate -- [options]`:
+//! - `--left <player>` and `--right <player>` pick who plays each side. A player is a difficulty
+//!   (`very-easy`, `easy`, `normal`, `hard` or `very-hard`), optionally followed by `+predict` for the AI that
+//!   predicts where the ball will go, `tracker` (a human who always moves towards the ball) or `idle`
+//!   (a human who never moves). Without them every difficulty plays every other one.
+//! - `--matches <n>` sets how many matches each pairing plays, 200 by default.
+//! - `--seed <n>` sets the seed of the first match, the ones after it use the seeds following it.
+
+use std::env;
+use std::process;
+
+use psp_pong::pong_controller::display::NullDisplay;
+use psp_pong::pong_controller::input::Buttons;
+use psp_pong::pong_controller::opponent::{OpponentAi, OpponentDifficulty};
+use psp_pong::pong_controller::score::Side;
+use psp_pong::{GameState, PongController};
+
+const UPDATES_PER_SECOND: u32 = 20; // the same as on the PSP, so the game plays the same way
+const MAX_STEPS_PER_MATCH: u32 = 1_000_000; // a match still going after this many updates is given up on
+const MAX_SPEED_BUCKET: usize = 12; // the fastest a ball can go, in pixels per update
+
+const DIFFICULTIES: [(&str, OpponentDifficulty); 5] = [
+    ("very-easy", OpponentDifficulty::VeryEasy),
+    ("easy", OpponentDifficulty::Easy),
+    ("normal", OpponentDifficulty::Normal),
+    ("hard", OpponentDifficulty::Hard),
+    ("very-hard", OpponentDifficulty::VeryHard),
+];
+
+/// Who plays one side of a simulated match.
+#[derive(Copy, Clone)]
+enum Player {
+    Computer(&'static str, OpponentDifficulty, OpponentAi),
+    Tracker, // a human paddle that moves towards the ball whenever it is heading its way
+    Idle, // a human paddle that never moves
+}
+
+impl Player {
+    fn parse(name: &str) -> Option<Self> {
+        let (difficulty, ai) = match name.strip_suffix("+predict") {
+            Some(difficulty) => (difficulty, OpponentAi::Predict),
+            None => (name, OpponentAi::Chase),
+        };
+        match name {
+            "tracker" => Some(Player::Tracker),
+            "idle" => Some(Player::Idle),
+            _ => DIFFICULTIES.iter()
+                .find(|&&(name, _)| name == difficulty)
+                .map(|&(name, difficulty)| Player::Computer(name, difficulty, ai)),
+        }
+    }
+
+    fn name(&self) -> String {
+        match self {
+            Player::Computer(name, _, OpponentAi::Chase) => name.to_string(),
+            Player::Computer(name, _, OpponentAi::Predict) => format!("{}+predict", name),
+            Player::Tracker => "tracker".to_string(),
+            Player::Idle => "idle".to_string(),
+        }
+    }
+
+    /// Returns the difficulty and AI the computer plays with, or `None` for a scripted player.
+    fn computer(&self) -> Option<(OpponentDifficulty, OpponentAi)> {
+        match self {
+            Player::Computer(_, difficulty, ai) => Some((*difficulty, *ai)),
+            Player::Tracker | Player::Idle => None,
+        }
+    }
+
+    /// Returns the buttons a scripted player holds to move the paddle on `side`.
+    fn buttons(&self, pong: &PongController<NullDisplay>, side: Side) -> Buttons {
+        let (up, down) = match side {
+            Side::Player => (Buttons::UP, Buttons::DOWN),
+            Side::Opponent => (Buttons::TRIANGLE, Buttons::CROSS),
+        };
+        let ball = &pong.balls()[0];
+        let heading_here = match side {
+            Side::Player => ball.get_velocity().x < 0.0,
+            Side::Opponent => ball.get_velocity().x > 0.0,
+        };
+        if !matches!(self, Player::Tracker) || !heading_here {
+            return Buttons::empty();
+        }
+
+        let paddle = pong.paddle(side).get_bounds();
+        let y = ball.get_position().y as i32;
+        if y < paddle.top + 5 {
+            up
+        } else if y > paddle.bottom - 5 {
+            down
+        } else {
+            Buttons::empty()
+        }
+    }
+}
+
+/// What happened over all the matches between two players.
+#[derive(Default)]
+struct Report {
+    matches: u32,
+    left_wins: u32,
+    right_wins: u32,
+    unfinished: u32, // matches given up on after MAX_STEPS_PER_MATCH updates
+    points: u32,
+    hits: u32, // times a paddle hit the ball
+    speeds: [u32; MAX_SPEED_BUCKET + 1], // how fast the ball was going after each hit, rounded to whole pixels per update
+}
+
+impl Report {
+    fn print(&self, left: Player, right: Player) {
+        let percent = |count: u32, total: u32| if total == 0 { 0.0 } else { 100.0 * count as f64 / total as f64 };
+        let rally = if self.points == 0 { 0.0 } else { self.hits as f64 / self.points as f64 };
+        let speeds: Vec<String> = self.speeds.iter()
+            .enumerate()
+            .filter(|&(_, &count)| count > 0)
+            .map(|(speed, &count)| format!("{}:{:.0}%", speed, percent(count, self.hits)))
+            .collect();
+        println!("{:<17} {:<17} {:>7} {:>8.1}% {:>8.1}% {:>10} {:>9.2}  {}",
+            left.name(), right.name(), self.matches, percent(self.left_wins, self.matches),
+            percent(self.right_wins, self.matches), self.unfinished, rally, speeds.join(" "));
+    }
+}
+
+/// Plays one match to the end and adds what happened to `report`.
+fn play_match(left: Player, right: Player, seed: u64, report: &mut Report) {
+    let mut pong = PongController::new(NullDisplay, UPDATES_PER_SECOND, seed);
+    pong.set_left_computer(left.computer());
+    pong.set_two_players(right.computer().is_none());
+    if let Some((difficulty, ai)) = right.computer() {
+        pong.set_difficulty(difficulty);
+        pong.set_opponent_ai(ai);
+    }
+    pong.start_match();
+    report.matches += 1;
+
+    let mut heading_left = pong.balls()[0].get_velocity().x < 0.0;
+    for _ in 0..MAX_STEPS_PER_MATCH {
+        let buttons = left.buttons(&pong, Side::Player) | right.buttons(&pong, Side::Opponent);
+        let was_playing = pong.state() == GameState::Playing;
+        pong.step(buttons);
+
+        match pong.state() {
+            GameState::GameOver(winner) => {
+                report.points += 1;
+                match winner {
+                    Side::Player => report.left_wins += 1,
+                    Side::Opponent => report.right_wins += 1,
+                }
+                return;
+            },
+            GameState::PointScored(_) if was_playing => report.points += 1,
+            GameState::Playing if was_playing => {
+                // Only the paddles send the ball back the way it came, as the walls cover the goals
+                let ball = &pong.balls()[0];
+                if (ball.get_velocity().x < 0.0) != heading_left {
+                    report.hits += 1;
+                    report.speeds[(ball.get_speed().round() as usize).min(MAX_SPEED_BUCKET)] += 1;
+                }
+            },
+            _ => {},
+        }
+        heading_left = pong.balls()[0].get_velocity().x < 0.0;
+    }
+    report.unfinished += 1;
+}
+
+/// Returns the value following `flag` in the arguments, if it was given.
+fn flag_value(args: &[String], flag: &str) -> Option<String> {
+    args.iter().position(|arg| arg == flag).map(|i| match args.get(i + 1) {
+        Some(value) => value.clone(),
+        None => fail(&format!("{} needs a value", flag)),
+    })
+}
+
+fn parse_player(name: &str) -> Player {
+    Player::parse(name).unwrap_or_else(|| fail(&format!("unknown player {}", name)))
+}
+
+fn parse_number(value: &str) -> u64 {
+    value.parse().unwrap_or_else(|_| fail(&format!("{} is not a number", value)))
+}
+
+fn fail(message: &str) -> ! {
+    eprintln!("{}", message);
+    eprintln!("usage: simulate [--left <player>] [--right <player>] [--matches <n>] [--seed <n>]");
+    process::exit(1);
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let matches = flag_value(&args, "--matches").map_or(200, |value| parse_number(&value));
+    let seed = flag_value(&args, "--seed").map_or(0, |value| parse_number(&value));
+    let left = flag_value(&args, "--left").map(|name| parse_player(&name));
+    let right = flag_value(&args, "--right").map(|name| parse_player(&name));
+
+    let computers: Vec<Player> = DIFFICULTIES.iter().map(|&(name, difficulty)| Player::Computer(name, difficulty, OpponentAi::Chase)).collect();
+    let lefts = left.map_or_else(|| computers.clone(), |player| vec![player]);
+    let rights = right.map_or_else(|| computers.clone(), |player| vec![player]);
+
+    println!("{:<17} {:<17} {:>7} {:>9} {:>9} {:>10} {:>9}  Ball speed after hits",
+        "Left", "Right", "Matches", "Left won", "Right won", "Unfinished", "Hits/pt");
+    for &left in &lefts {
+        for &right in &rights {
+            let mut report = Report::default();
+            for i in 0..matches {
+                play_match(left, right, seed.wrapping_add(i), &mut report);
+            }
+            report.print(left, right);
+        }
+    }
+}
@@ -0,0 +1,310 @@
+//! Plays the game in a terminal on the host, so changes to the gameplay can be tried without a PSP.
+//! Run with `cargo run --example terminal`. The screen is scaled down to fit the terminal and drawn with
+//! half-block characters in 24-bit colour, so the terminal has to support both.
+//!
+//! Keys: w/s or Up/Down move the left paddle, i/k move the right paddle, p is Start, Tab is Select,
+//! Enter/Space/x is Cross, Backspace/o is Circle, a/d or Left/Right change menu values, q/e are L/R and
+//! Ctrl-C quits.
+
+use std::io::{self, Read, Write};
+use std::process::{self, Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use embedded_graphics::drawable::Pixel;
+use embedded_graphics::geometry::Size;
+use embedded_graphics::pixelcolor::{Rgb888, RgbColor};
+use embedded_graphics::DrawTarget;
+use psp_pong::pong_controller::clock::Clock;
+use psp_pong::pong_controller::display::{MemoryDisplay, Screen, PIXEL_COUNT};
+use psp_pong::pong_controller::input::{Buttons, InputSource};
+use psp_pong::{PongController, SCREEN_HEIGHT, SCREEN_WIDTH};
+
+const UPDATES_PER_SECOND: u32 = 20; // the same as on the PSP, so the game plays at the same speed
+const FRAME_TIME: Duration = Duration::from_millis(1000 / 30); // terminals can't keep up with much more than 30 frames a second
+const HOLD_TIME: Duration = Duration::from_millis(150); // how long a key counts as held after the terminal last reported it
+const CTRL_C: u8 = 3;
+
+/// The button each key is mapped to. Arrow keys are handled separately as they arrive as escape sequences.
+const KEYS: [(u8, Buttons); 15] = [
+    (b'w', Buttons::UP),
+    (b's', Buttons::DOWN),
+    (b'a', Buttons::LEFT),
+    (b'd', Buttons::RIGHT),
+    (b'i', Buttons::TRIANGLE),
+    (b'k', Buttons::CROSS),
+    (b'x', Buttons::CROSS),
+    (b' ', Buttons::CROSS),
+    (b'\r', Buttons::CROSS),
+    (b'o', Buttons::CIRCLE),
+    (0x7f, Buttons::CIRCLE), // backspace
+    (b'p', Buttons::START),
+    (b'\t', Buttons::SELECT),
+    (b'q', Buttons::LTRIGGER),
+    (b'e', Buttons::RTRIGGER),
+];
+
+/// Reads the time from the host's monotonic clock.
+struct HostClock {
+    start: Instant,
+}
+
+impl Clock for HostClock {
+    fn now(&self) -> u64 {
+        self.start.elapsed().as_micros() as u64
+    }
+}
+
+/// Reads key presses from the terminal. Terminals only report keys going down, and repeat them while
+/// they are held, so a key counts as held until it hasn't been seen for `HOLD_TIME`. Only the last key
+/// pressed is repeated, so holding two keys at once doesn't work in most terminals.
+struct TerminalInput {
+    keys: Receiver<Buttons>,
+    held: Vec<(Buttons, Instant)>, // buttons that are held and when they were last seen
+}
+
+impl TerminalInput {
+    /// Starts reading the keys on a thread of their own, so the game never waits on them.
+    fn new(quit: Arc<AtomicBool>) -> Self {
+        let (sender, keys) = mpsc::channel();
+        thread::spawn(move || {
+            let mut bytes = [0; 64];
+            loop {
+                let count = match io::stdin().lock().read(&mut bytes) {
+                    Ok(0) | Err(_) => break,
+                    Ok(count) => count,
+                };
+                if bytes[..count].contains(&CTRL_C) {
+                    quit.store(true, Ordering::Relaxed);
+                    break;
+                }
+                for buttons in parse_keys(&bytes[..count]) {
+                    if sender.send(buttons).is_err() {
+                        return;
+                    }
+                }
+            }
+        });
+        Self { keys, held: Vec::new() }
+    }
+}
+
+impl InputSource for TerminalInput {
+    fn read(&mut self) -> Buttons {
+        let now = Instant::now();
+        for buttons in self.keys.try_iter() {
+            self.held.retain(|&(held, _)| held != buttons);
+            self.held.push((buttons, now));
+        }
+        self.held.retain(|&(_, seen)| now - seen < HOLD_TIME);
+        self.held.iter().fold(Buttons::empty(), |all, &(buttons, _)| all | buttons)
+    }
+}
+
+/// Returns the buttons for the keys in a chunk of input read from the terminal.
+fn parse_keys(bytes: &[u8]) -> Vec<Buttons> {
+    let mut keys = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == 0x1b && bytes.get(i + 1) == Some(&b'[') { // an arrow key
+            let arrow = match bytes.get(i + 2) {
+                Some(b'A') => Buttons::UP,
+                Some(b'B') => Buttons::DOWN,
+                Some(b'C') => Buttons::RIGHT,
+                Some(b'D') => Buttons::LEFT,
+                _ => Buttons::empty(),
+            };
+            if !arrow.is_empty() {
+                keys.push(arrow);
+            }
+            i += 3;
+            continue;
+        }
+        let key = bytes[i].to_ascii_lowercase();
+        if let Some(&(_, buttons)) = KEYS.iter().find(|&&(k, _)| k == key) {
+            keys.push(buttons);
+        }
+        i += 1;
+    }
+    keys
+}
+
+/// Draws the game into memory and shows it in the terminal, scaled down to `columns` by `rows` characters.
+/// Every character shows two pixels stacked on top of each other, as the upper half block takes the
+/// foreground colour and the rest of the cell the background colour.
+struct TerminalScreen {
+    frame: MemoryDisplay<'static>,
+    columns: usize,
+    rows: usize,
+    last_output: String, // what was written for the last frame, so an unchanged frame isn't written again
+    next_frame: Instant,
+    quit: Arc<AtomicBool>,
+    saved_settings: String, // the terminal's settings from before the game started, to put back when it quits
+}
+
+impl TerminalScreen {
+    fn new(columns: usize, rows: usize, saved_settings: String, quit: Arc<AtomicBool>) -> Self {
+        // The game never gives up the screen, so the memory is left alive for the rest of the program
+        let pixels = Box::leak(vec![Rgb888::BLACK; PIXEL_COUNT].into_boxed_slice());
+        Self {
+            frame: MemoryDisplay::new(pixels),
+            columns,
+            rows,
+            last_output: String::new(),
+            next_frame: Instant::now(),
+            quit,
+            saved_settings,
+        }
+    }
+
+    /// Returns the average colour of the pixels that end up in the scaled down pixel at `column`, `row`.
+    fn sample(&self, column: usize, row: usize) -> Rgb888 {
+        let width = SCREEN_WIDTH as usize;
+        let (left, right) = span(column, self.columns, width);
+        let (top, bottom) = span(row, self.rows * 2, SCREEN_HEIGHT as usize);
+
+        let pixels = self.frame.pixels();
+        let (mut r, mut g, mut b) = (0u32, 0u32, 0u32);
+        for y in top..bottom {
+            for pixel in &pixels[y * width + left..y * width + right] {
+                r += pixel.r() as u32;
+                g += pixel.g() as u32;
+                b += pixel.b() as u32;
+            }
+        }
+        let count = ((right - left) * (bottom - top)) as u32;
+        Rgb888::new((r / count) as u8, (g / count) as u8, (b / count) as u8)
+    }
+
+    /// Turns the frame into the characters and colour codes that draw it.
+    fn rasterise(&self) -> String {
+        let mut output = String::from("\x1b[H");
+        let mut colours = None; // the colours last switched to, which don't have to be repeated
+        for row in 0..self.rows {
+            for column in 0..self.columns {
+                let (upper, lower) = (self.sample(column, row * 2), self.sample(column, row * 2 + 1));
+                if colours != Some((upper, lower)) {
+                    output.push_str(&format!("\x1b[38;2;{};{};{}m\x1b[48;2;{};{};{}m",
+                        upper.r(), upper.g(), upper.b(), lower.r(), lower.g(), lower.b()));
+                    colours = Some((upper, lower));
+                }
+                output.push('▀');
+            }
+            output.push_str("\x1b[0m\r\n");
+            colours = None;
+        }
+        output
+    }
+}
+
+impl DrawTarget<Rgb888> for TerminalScreen {
+    type Error = core::convert::Infallible;
+
+    fn draw_pixel(&mut self, pixel: Pixel<Rgb888>) -> Result<(), Self::Error> {
+        self.frame.draw_pixel(pixel)
+    }
+
+    fn clear(&mut self, color: Rgb888) -> Result<(), Self::Error> {
+        self.frame.clear(color)
+    }
+
+    fn size(&self) -> Size {
+        self.frame.size()
+    }
+}
+
+impl Screen for TerminalScreen {
+    fn present(&mut self) {
+        if self.quit.load(Ordering::Relaxed) { // Quitting is done here so a frame is never cut off halfway
+            restore_terminal(&self.saved_settings);
+            process::exit(0);
+        }
+
+        // Waits for the next frame the way the PSP waits for the vertical blank
+        let now = Instant::now();
+        if now < self.next_frame {
+            thread::sleep(self.next_frame - now);
+        }
+        self.next_frame = Instant::now() + FRAME_TIME;
+
+        let output = self.rasterise();
+        if output != self.last_output {
+            let stdout = io::stdout();
+            let mut lock = stdout.lock();
+            let _ = lock.write_all(output.as_bytes());
+            let _ = lock.flush();
+            self.last_output = output;
+        }
+    }
+}
+
+/// Returns the range of the `length` pixels that the `index`th of `count` scaled down pixels covers.
+/// Every scaled down pixel covers at least one.
+fn span(index: usize, count: usize, length: usize) -> (usize, usize) {
+    let start = index * length / count;
+    (start, ((index + 1) * length / count).max(start + 1))
+}
+
+/// Runs `stty` on the terminal with the given arguments and returns what it printed.
+fn stty(args: &[&str]) -> Option<String> {
+    let output = Command::new("stty").args(args).stdin(Stdio::inherit()).output().ok()?;
+    if output.status.success() {
+        Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    } else {
+        None
+    }
+}
+
+/// Puts the terminal's settings back and leaves the game's screen.
+fn restore_terminal(saved_settings: &str) {
+    print!("\x1b[0m\x1b[?25h\x1b[?1049l"); // resets the colours, shows the cursor and goes back to the normal screen
+    let _ = io::stdout().flush();
+    stty(&[saved_settings]);
+}
+
+/// Returns how many columns and rows of characters the game can take up while keeping the screen's shape.
+fn screen_size() -> (usize, usize) {
+    let (rows, columns) = stty(&["size"])
+        .and_then(|size| {
+            let mut parts = size.split_whitespace().map(|part| part.parse::<usize>().ok());
+            Some((parts.next()??, parts.next()??))
+        })
+        .unwrap_or((34, 120));
+
+    // Every character is about twice as tall as it is wide, so it holds two square pixels
+    let width = SCREEN_WIDTH as usize;
+    let height = SCREEN_HEIGHT as usize;
+    let columns = columns.min(rows * 2 * width / height).clamp(1, width);
+    let rows = (columns * height / width / 2).max(1);
+    (columns, rows)
+}
+
+fn main() {
+    let saved_settings = match stty(&["-g"]) {
+        Some(settings) => settings,
+        None => {
+            eprintln!("The terminal frontend has to be run in a terminal.");
+            process::exit(1);
+        }
+    };
+    stty(&["raw", "-echo"]);
+    print!("\x1b[?1049h\x1b[?25l\x1b[2J"); // switches to a screen of its own, hides the cursor and clears it
+    let _ = io::stdout().flush();
+
+    let hook_settings = saved_settings.clone();
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        restore_terminal(&hook_settings);
+        default_hook(info);
+    }));
+
+    let quit = Arc::new(AtomicBool::new(false));
+    let (columns, rows) = screen_size();
+    let screen = TerminalScreen::new(columns, rows, saved_settings, quit.clone());
+    let seed = SystemTime::now().duration_since(UNIX_EPOCH).map(|time| time.as_micros() as u64).unwrap_or(0);
+    let mut pong = PongController::new(screen, UPDATES_PER_SECOND, seed);
+    pong.run(&mut TerminalInput::new(quit), &HostClock { start: Instant::now() });
+}
@@ -0,0 +1,9 @@
+//! A pong game drawn with embedded-graphics. The game itself does not depend on the PSP, which is only
+//! used for input, timing and the screen when the `psp` feature is turned on.
+#![no_std]
+#![cfg_attr(feature = "psp", feature(min_const_generics))] // const generics still need the gate on the PSP's nightly
+
+pub mod pong_controller;
+
+pub use crate::pong_controller::pong_controller::{PongController, GameState, GameMode, BallSpawn, ServeRule, PaddleControl};
+pub use crate::pong_controller::{SCREEN_WIDTH, SCREEN_HEIGHT};
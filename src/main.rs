@@ -1,22 +1,17 @@
 #![no_std]
 #![no_main]
-#![feature(min_const_generics)]
-mod pong_controller;
 
-use psp;
-use embedded_graphics::{
-    style::{PrimitiveStyleBuilder, TextStyleBuilder},
-    primitives::{circle::Circle,rectangle::Rectangle},
-    prelude::*,
-    pixelcolor::Rgb888,
-    fonts::{Font6x8,Text},
-    image::Image};
-use crate::pong_controller::pong_controller::PongController;
+use psp_pong::PongController;
+use psp_pong::pong_controller::input::PspInput;
+use psp_pong::pong_controller::clock::{Clock, PspClock};
+use psp_pong::pong_controller::display::PspScreen;
 
 psp::module!("psp pong", 1, 1);
 
 fn psp_main() {
     psp::enable_home_button();
-    let mut pong = PongController::new(20);
-    pong.run();
+    let clock = PspClock::new();
+    let seed = clock.now(); // the PSP's clock will be utilized for creating the seed for initializing the rng
+    let mut pong = PongController::new(PspScreen::new(), 20, seed);
+    pong.run(&mut PspInput::new(), &clock);
 }
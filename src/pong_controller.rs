@@ -1,229 +1,1424 @@
-mod paddle;
-mod ball;
-mod opponent;
-mod menu;
+pub mod paddle;
+pub mod ball;
+pub mod opponent;
+pub mod menu;
+pub mod input;
+pub mod clock;
+mod timestep;
+pub mod vector;
+mod collision;
+pub mod display;
+pub mod score;
 
+/// Width of the PSP's screen in pixels.
+pub const SCREEN_WIDTH: u32 = 480;
+/// Height of the PSP's screen in pixels.
+pub const SCREEN_HEIGHT: u32 = 272;
+
+#[allow(clippy::module_inception)]
 pub mod pong_controller {
     use crate::pong_controller::paddle::{Paddle, PaddleBuilder, PaddleMovementSpeed};
-    use crate::pong_controller::opponent::{Opponent, OpponentDifficulty};
+    use crate::pong_controller::opponent::{Opponent, OpponentAi, OpponentDifficulty};
     use crate::pong_controller::ball::{Ball, BallAxes};
-    use psp::sys::{SceCtrlData, CtrlButtons};
-    use psp::sys::{sceRtcGetCurrentTick as getTick, sceCtrlReadBufferPositive as getInput};
-    use psp::embedded_graphics::Framebuffer;
-    use embedded_graphics::style::{TextStyleBuilder, PrimitiveStyleBuilder};
-    use embedded_graphics::fonts::{Font6x12, Text, Font8x16, Font12x16};
+    use crate::pong_controller::input::{Buttons, InputState};
+    use crate::pong_controller::{SCREEN_WIDTH, SCREEN_HEIGHT};
+    use crate::pong_controller::input::InputSource;
+    use crate::pong_controller::clock::{self, Clock};
+    use crate::pong_controller::timestep::FixedTimestep;
+    use crate::pong_controller::collision::{self, Aabb, Hit};
+    use embedded_graphics::style::{TextStyle, TextStyleBuilder, PrimitiveStyleBuilder};
+    use embedded_graphics::primitives::Rectangle;
+    use embedded_graphics::fonts::{Font6x12, Text, Font12x16};
     use embedded_graphics::pixelcolor::Rgb888;
-    use embedded_graphics::prelude::{RgbColor, Point};
+    use embedded_graphics::prelude::{RgbColor, Point, Size, Primitive};
     use embedded_graphics::drawable::Drawable;
-    use arrayvec::ArrayString;
+    use embedded_graphics::DrawTarget;
+    use arrayvec::{ArrayString, ArrayVec};
     use core::fmt::Write;
+    use core::cmp::Ordering;
+    use crate::pong_controller::vector::Vec2;
     use rand_chacha::{ChaCha20Rng, ChaChaRng};
-    use rand::SeedableRng;
-    use crate::pong_controller::menu::Menu;
+    use rand::{Rng, SeedableRng};
+    use crate::pong_controller::menu::{Menu, MenuBuilder, MenuItem};
+    use crate::pong_controller::score::{MatchRules, Score, Side};
+    use crate::pong_controller::display::Screen;
+
+    const MAX_CATCH_UP_STEPS: u32 = 5; // the most updates run back to back when the game falls behind
+    const SERVE_CONE: f32 = core::f32::consts::FRAC_PI_4; // serves go at most 45 degrees away from straight across
+    const BALL_SPEED: f32 = 3.0; // pixels per update the ball is served at
+    const MAX_BALL_SPEED: f32 = 12.0;
+    const MAX_BOUNCES_PER_STEP: u32 = 4; // the most things the ball can bounce off during one update
+    const MAX_BALLS: usize = 4; // the most balls that can be in play at once
+    // The game modes that can be picked with the multi-ball option in the menu, along with their names
+    const MULTI_BALL_CHOICES: [(&str, MainMenuAction); 3] = [
+        ("Off", MainMenuAction::MultiBall(GameMode::Classic)),
+        ("Timed", MainMenuAction::MultiBall(GameMode::MultiBall(BallSpawn::Timer(5000)))),
+        ("Hits", MainMenuAction::MultiBall(GameMode::MultiBall(BallSpawn::PaddleHits(5)))),
+    ];
+    const MIN_SERVE_SPEED: i32 = 1; // slowest serve speed that can be set in the menu
+    const MAX_SERVE_SPEED: i32 = 6; // fastest serve speed that can be set in the menu
+    const DEFAULT_RULES: MatchRules = MatchRules::new(11, true, 1);
+    const SERVE_COUNTDOWN: u64 = 3_000_000; // microseconds counted down in the middle of the screen before the ball is served
+    const SERVE_RULE_CHOICES: [(&str, MainMenuAction); 2] = [
+        ("Loser", MainMenuAction::ServeTo(ServeRule::Loser)),
+        ("Alternate", MainMenuAction::ServeTo(ServeRule::Alternate)),
+    ];
+    const AI_CHOICES: [(&str, MainMenuAction); 2] = [
+        ("Chase", MainMenuAction::OpponentAi(OpponentAi::Chase)),
+        ("Predict", MainMenuAction::OpponentAi(OpponentAi::Predict)),
+    ];
+    const POINT_DELAY: u64 = 1_000_000; // microseconds everything waits after a point is scored
+    const MAX_POINTS_TO_WIN: i32 = 21; // most points to win that can be set in the menu
+    // How many sets a match can last, picked in the menu
+    const BEST_OF_CHOICES: [(&str, MainMenuAction); 4] = [
+        ("1", MainMenuAction::BestOf(1)),
+        ("3", MainMenuAction::BestOf(3)),
+        ("5", MainMenuAction::BestOf(5)),
+        ("7", MainMenuAction::BestOf(7)),
+    ];
+    // The walls are boxes just off the top and bottom of the screen, wide enough to cover the goals too
+    const TOP_WALL: Aabb = Aabb::new(-(SCREEN_WIDTH as f32), -(SCREEN_HEIGHT as f32), 2.0*SCREEN_WIDTH as f32, 0.0);
+    const BOTTOM_WALL: Aabb = Aabb::new(-(SCREEN_WIDTH as f32), SCREEN_HEIGHT as f32, 2.0*SCREEN_WIDTH as f32, 2.0*SCREEN_HEIGHT as f32);
+
+    /// Something the ball can bounce off.
+    #[derive(Copy, Clone)]
+    enum Surface {
+        Wall,
+        Player,
+        Opponent,
+    }
+
+    /// When extra balls are put into play during a multi-ball game.
+    #[derive(Copy, Clone, PartialEq)]
+    pub enum BallSpawn {
+        Timer(u32), // every this many milliseconds
+        PaddleHits(u32), // every this many times a paddle hits a ball
+    }
+
+    #[derive(Copy, Clone, PartialEq)]
+    pub enum GameMode {
+        Classic, // one ball in play
+        MultiBall(BallSpawn), // up to MAX_BALLS balls in play
+    }
+
+    /// Who the ball is served towards after a point.
+    #[derive(Copy, Clone, PartialEq)]
+    pub enum ServeRule {
+        Loser, // towards whoever let the last point in
+        Alternate, // towards each side in turn
+    }
+
+    /// What the items of the main menu do. The ones picked from a list carry the picked value.
+    #[derive(Copy, Clone, PartialEq)]
+    enum MainMenuAction {
+        SetDifficulty,
+        OpponentAi(OpponentAi),
+        MultiBall(GameMode),
+        TwoPlayers,
+        BallSpeed,
+        PointsToWin,
+        WinByTwo,
+        BestOf(u32),
+        ServeTo(ServeRule),
+        ManualServe,
+        ResetScore,
+        Resume,
+    }
+
+    /// What the items of the title screen's menu do.
+    #[derive(Copy, Clone)]
+    enum TitleAction {
+        OnePlayer,
+        TwoPlayers,
+        Settings,
+    }
+
+    /// What the items of the menu shown at the end of a match do.
+    #[derive(Copy, Clone)]
+    enum GameOverAction {
+        Rematch,
+        Settings,
+        Title,
+    }
+
+    /// The phases the game goes through. Each has hooks for entering it, updating it and leaving it.
+    #[derive(Copy, Clone, PartialEq, Debug)]
+    pub enum GameState {
+        Title, // waiting for a match to be started from the title screen
+        Serve(u64), // the ball waits in the middle for this many more microseconds, or until the server serves it by hand
+        Playing, // the ball is in play
+        PointScored(u64), // everything waits this many more microseconds after a point
+        Paused, // nothing moves; the menu can be opened from here
+        GameOver(Side), // the match was won by a side
+    }
 
-    struct Score {
-        player: u32,
-        opponent: u32,
+    /// The menus that can be opened.
+    #[derive(Copy, Clone, PartialEq)]
+    enum MenuId {
+        Title,
+        Main,
+        Difficulty,
+        GameOver,
     }
 
-    pub struct PongController{
-        player: Paddle,
-        opponent: Opponent,
-        ball: Ball,
+    /// Who controls one of the paddles.
+    pub enum PaddleControl {
+        Computer(Opponent),
+        Human(Paddle), // the d-pad on the left, or a second player using triangle and cross on the right
+    }
+
+    impl PaddleControl {
+        /// Gets an immutable reference to the paddle.
+        pub fn get_paddle(&self) -> &Paddle{
+            match self{
+                PaddleControl::Computer(opponent) => opponent.get_paddle(),
+                PaddleControl::Human(paddle) => paddle,
+            }
+        }
+
+        /// Keeps the paddle where it is for this update
+        fn stay(&mut self){
+            match self{
+                PaddleControl::Computer(opponent) => opponent.stay(),
+                PaddleControl::Human(paddle) => paddle.stay(),
+            }
+        }
+
+        /// Draws the paddle
+        fn draw<D: DrawTarget<Rgb888>>(&self, disp: &mut D, alpha: f32){
+            match self{
+                PaddleControl::Computer(opponent) => opponent.draw(disp, alpha),
+                PaddleControl::Human(paddle) => paddle.draw(disp, alpha),
+            }
+        }
+    }
+
+    pub struct PongController<D: Screen>{
+        player: PaddleControl, // the left paddle
+        opponent: PaddleControl, // the right paddle
+        difficulty: OpponentDifficulty, // how hard the computer is when it controls the right paddle
+        ai: OpponentAi, // how the computer plays when it controls the right paddle
+        balls: ArrayVec<[Ball; MAX_BALLS]>,
+        game_mode: GameMode,
+        ball_speed: f32, // pixels per update balls are served at
+        spawn_counter: u64, // time or paddle hits since a ball was last spawned, depending on the game mode
         score: Score,
-        ticks_per_update: u32,
-        display: Framebuffer,
+        rules: MatchRules,
+        serve_rule: ServeRule,
+        serve_to: Side, // who the next serve heads towards
+        manual_serve: bool, // a human serving waits for a button instead of the countdown
+        score_style: TextStyle<Rgb888, Font6x12>,
+        update_interval: u64, // microseconds between updates
+        display: D,
         rng: ChaCha20Rng, // A random number generator that initialized at runtime
+        main_menu: Menu<'static, MainMenuAction, Font12x16, Font12x16, 12>,
+        difficulty_menu: Menu<'static, OpponentDifficulty, Font12x16, Font12x16, 5>,
+        title_menu: Menu<'static, TitleAction, Font12x16, Font12x16, 3>,
+        game_over_menu: Menu<'static, GameOverAction, Font12x16, Font12x16, 3>,
+        input: InputState,
+        state: GameState,
+        paused_state: GameState, // what the game goes back to when it is resumed
+        menu_stack: ArrayVec<[MenuId; 3]>, // the open menus, with the one being shown last. Any open menu pauses all other events
     }
 
-    impl PongController{
-        pub fn new(ticks_per_second: u32) -> Self{
-            let player = PaddleBuilder::new()
+    impl<D: Screen> PongController<D>{
+        /// Creates a new game drawing to `display` that updates `updates_per_second` times a second.
+        /// The `seed` initializes the rng used for serving the ball. Panics if `updates_per_second` is 0.
+        pub fn new(display: D, updates_per_second: u32, seed: u64) -> Self{
+            assert!(updates_per_second > 0, "A PongController has to update at least once per second.");
+            let player = PaddleControl::Human(PaddleBuilder::new()
                 .set_default_dimensions()
                 .set_speed(PaddleMovementSpeed::Normal)
                 .set_x(10)
-                .set_y(psp::SCREEN_HEIGHT as i32/2)
-                .build();
+                .set_y(SCREEN_HEIGHT as i32/2)
+                .build());
 
-            let mut seed = 0;
-            unsafe { getTick(&mut seed)}; // the PSP's clock tick will be utilized for creating the seed for initializing the rng
             let mut rng = ChaChaRng::seed_from_u64(seed);
 
-            let opponent = Opponent::new(OpponentDifficulty::Hard);
-            let ball = Ball::new(psp::SCREEN_WIDTH as i32/2, psp::SCREEN_HEIGHT as i32/2, 10, 3, &mut rng);
-            let score = Score {player: 0, opponent: 0};
-            let ticks_per_update = 1000/ticks_per_second; //gets the amount of time between screen updates
+            let difficulty = OpponentDifficulty::Hard;
+            let opponent = PaddleControl::Computer(Opponent::new(difficulty));
+            let mut balls = ArrayVec::new();
+            balls.push(Self::new_ball(&mut rng, BALL_SPEED));
+            let score = Score::new();
+            let score_style = TextStyleBuilder::new(Font6x12)
+                .background_color(Rgb888::BLACK)
+                .text_color(Rgb888::WHITE)
+                .build();
+            let update_interval = clock::interval(updates_per_second); //gets the amount of time between screen updates
 
+            let main_menu = MenuBuilder::new()
+                .set_title("Main Menu")
+                .set_items([
+                    MenuItem::button("Set Difficulty", MainMenuAction::SetDifficulty),
+                    MenuItem::picker("Opponent AI", &AI_CHOICES, 0),
+                    MenuItem::picker("Multi-Ball", &MULTI_BALL_CHOICES, 0),
+                    MenuItem::toggle("Two Players", MainMenuAction::TwoPlayers, false),
+                    MenuItem::slider("Ball Speed", MainMenuAction::BallSpeed, MIN_SERVE_SPEED, MAX_SERVE_SPEED, 1, BALL_SPEED as i32),
+                    MenuItem::slider("Points to Win", MainMenuAction::PointsToWin, 1, MAX_POINTS_TO_WIN, 1, DEFAULT_RULES.points_to_win as i32),
+                    MenuItem::toggle("Win by Two", MainMenuAction::WinByTwo, DEFAULT_RULES.win_by_two),
+                    MenuItem::picker("Best Of", &BEST_OF_CHOICES, DEFAULT_RULES.best_of as usize/2),
+                    MenuItem::picker("Serve To", &SERVE_RULE_CHOICES, 0),
+                    MenuItem::toggle("Manual Serve", MainMenuAction::ManualServe, false),
+                    MenuItem::button("Reset Score", MainMenuAction::ResetScore),
+                    MenuItem::button("Resume", MainMenuAction::Resume),
+                ])
+                .set_item_font(Font12x16)
+                .set_title_font(Font12x16)
+                .set_position(Point::new(SCREEN_WIDTH as i32/2 - 150, 20))
+                .set_size(Size::new(300, SCREEN_HEIGHT - 40)) // wide enough for the values next to the items
+                .set_item_spacing(17)
+                .set_wrapping(true)
+                .build();
+            let difficulty_menu = MenuBuilder::new()
+                .set_title("Difficulty Settings")
+                .set_items([
+                    ("Very Easy", OpponentDifficulty::VeryEasy),
+                    ("Easy", OpponentDifficulty::Easy),
+                    ("Normal", OpponentDifficulty::Normal),
+                    ("Hard", OpponentDifficulty::Hard),
+                    ("Very Hard", OpponentDifficulty::VeryHard),
+                ])
+                .set_item_font(Font12x16)
+                .set_title_font(Font12x16)
+                .set_item_spacing(17)
+                .set_wrapping(true)
+                .build();
+            let title_menu = MenuBuilder::new()
+                .set_title("Start")
+                .set_items([
+                    ("One Player", TitleAction::OnePlayer),
+                    ("Two Players", TitleAction::TwoPlayers),
+                    ("Settings", TitleAction::Settings),
+                ])
+                .set_item_font(Font12x16)
+                .set_title_font(Font12x16)
+                .set_item_spacing(17)
+                .set_wrapping(true)
+                .set_position(Point::new(SCREEN_WIDTH as i32/2 - 100, SCREEN_HEIGHT as i32/2))
+                .set_size(Size::new(200, 80))
+                .build();
+            let game_over_menu = MenuBuilder::new()
+                .set_title("Game Over")
+                .set_items([
+                    ("Rematch", GameOverAction::Rematch),
+                    ("Settings", GameOverAction::Settings),
+                    ("Title Screen", GameOverAction::Title),
+                ])
+                .set_item_font(Font12x16)
+                .set_title_font(Font12x16)
+                .set_item_spacing(17)
+                .set_wrapping(true)
+                .set_position(Point::new(SCREEN_WIDTH as i32/2 - 100, SCREEN_HEIGHT as i32/2))
+                .set_size(Size::new(200, 80))
+                .build();
 
+            Self {player, opponent, difficulty, ai: OpponentAi::Chase, balls, game_mode: GameMode::Classic, ball_speed: BALL_SPEED, spawn_counter: 0, score, rules: DEFAULT_RULES, serve_rule: ServeRule::Loser,
+                serve_to: Side::Player, manual_serve: false, score_style, update_interval, display, rng,
+                main_menu, difficulty_menu, title_menu, game_over_menu, input: InputState::new(), state: GameState::Title,
+                paused_state: GameState::Playing, menu_stack: ArrayVec::new()}
+        }
 
-            Self {player, opponent, ball, score, ticks_per_update , display: Framebuffer::new(), rng}
+        /// Runs the game forever, reading the buttons from `input` and timing updates with `clock`.
+        pub fn run<I: InputSource, C: Clock>(&mut self, input: &mut I, clock: &C){
+            let mut timestep = FixedTimestep::new(self.update_interval, clock.now(), MAX_CATCH_UP_STEPS);
+            loop {
+                let steps = timestep.advance(clock.now());
+                if steps > 0{
+                    let buttons = input.read(); // updates the buttons currently being pressed
+                    for _ in 0..steps{
+                        self.step(buttons);
+                    }
+                }
+                self.render(timestep.alpha());
+            }
         }
 
-        pub fn run(&mut self){
-            let score_style = TextStyleBuilder::new(Font6x12)
-                .background_color(Rgb888::BLACK)
-                .text_color(Rgb888::WHITE)
-                .build();
+        /// Runs a single fixed-length update of the game with the buttons that are currently held down.
+        /// Nothing is drawn; that is left to `render`.
+        pub fn step(&mut self, buttons: Buttons){
+            self.input.update(buttons);
 
+            if !self.menu_stack.is_empty(){ // If a menu has been opened
+                self.step_menu();
+                return; // All other events will not execute as long as the window is not closed
+            }
+            self.update_state();
+        }
+
+        /// Moves the game into `next`, leaving the current state and entering the new one.
+        fn set_state(&mut self, next: GameState){
+            let previous = self.state;
+            self.exit_state();
+            self.state = next;
+            self.enter_state(previous);
+        }
 
-            let mut last_tick = 0;
-            let mut current_tick = 0;
-            let mut input = SceCtrlData::default();
-            let mut player_score_buf = ArrayString::<[_;12]>::new();
-            let mut opponent_score_buf = ArrayString::<[_;12]>::new();
+        /// Runs once when the game enters the current state, coming from `previous`.
+        fn enter_state(&mut self, previous: GameState){
+            match self.state{
+                GameState::Title => {
+                    self.score.reset();
+                    self.reset_balls();
+                },
+                GameState::Serve(_) => {
+                    if previous != GameState::Paused{ // a paused serve carries on where it was
+                        self.reset_balls();
+                    }
+                },
+                GameState::Playing => {},
+                GameState::PointScored(_) => self.hold_still(),
+                GameState::Paused => {
+                    self.paused_state = previous;
+                    self.hold_still();
+                },
+                GameState::GameOver(_) => {
+                    self.push_menu(MenuId::GameOver);
+                },
+            }
+        }
 
-            let mut player_score = Text::new("0", Point::new(5, 5))
-                .into_styled(score_style);
-            let mut opponent_score = Text::new("0", Point::new(psp::SCREEN_WIDTH as i32 - 50, 5))
-                .into_styled(score_style);
+        /// Runs once when the game leaves the current state.
+        fn exit_state(&mut self){
+            match self.state{
+                GameState::Title | GameState::Paused | GameState::GameOver(_) => self.close_menu(),
+                GameState::Serve(_) | GameState::Playing | GameState::PointScored(_) => {},
+            }
+        }
 
-            let mut main_menu = Menu::new("Main Menu", ["Set Difficulty", "Reset Score", "Close Menu"], Font12x16, Font12x16,17 );
-            let mut difficulty_menu = Menu::new("Difficulty Settings", ["Very Easy", "Easy", "Normal", "Hard", "Very Hard"], Font12x16, Font12x16, 17);
-            let mut menu_f = false; // flag to check if the menu should be open, pausing all other events
-            let mut menu_df = false; // flag to check if down on the d-pad was pressed.
-            let mut menu_uf = false; // flag to check if up on the d-pad was pressed.
-            let mut menu_sf = false; // flag to check that x was pressed.
+        /// Runs the current state for one update.
+        fn update_state(&mut self){
+            match self.state{
+                GameState::Title => self.push_menu(MenuId::Title), // the title menu stays open until a match is started
+                GameState::Serve(remaining) => {
+                    if self.input.pressed(Buttons::START){
+                        self.pause();
+                        return;
+                    }
+                    self.move_paddles();
+                    if let Some(button) = self.serve_button(){
+                        if self.input.pressed(button){
+                            self.set_state(GameState::Playing);
+                        }
+                    } else if remaining <= self.update_interval{
+                        self.set_state(GameState::Playing);
+                    } else {
+                        self.state = GameState::Serve(remaining - self.update_interval); // counting down is not a change of state
+                    }
+                },
+                GameState::Playing => {
+                    if self.input.pressed(Buttons::START){
+                        self.pause();
+                        return;
+                    }
+                    self.update_rally();
+                },
+                GameState::PointScored(remaining) => {
+                    if self.input.pressed(Buttons::START){
+                        self.pause();
+                        return;
+                    }
+                    self.move_paddles();
+                    if remaining <= self.update_interval{
+                        self.set_state(GameState::Serve(SERVE_COUNTDOWN));
+                    } else {
+                        self.state = GameState::PointScored(remaining - self.update_interval);
+                    }
+                },
+                GameState::Paused => {
+                    if self.input.pressed(Buttons::START){
+                        self.resume();
+                    } else if self.input.pressed(Buttons::SELECT){
+                        self.push_menu(MenuId::Main);
+                    }
+                },
+                GameState::GameOver(_) => self.push_menu(MenuId::GameOver), // nothing moves until the next match is started from the menu
+            }
+        }
 
-            unsafe { getTick(&mut last_tick) };
-            loop {
+        /// Moves the balls and paddles during a rally, keeping score.
+        fn update_rally(&mut self){
+            // Time to move the balls
+            let mut i = 0;
+            while i < self.balls.len(){
+                if self.step_ball(i) && matches!(self.game_mode, GameMode::MultiBall(BallSpawn::PaddleHits(_))){
+                    self.spawn_counter += 1;
+                }
 
-                unsafe {
-                    getTick(&mut current_tick); //updates the current tick
-                    getInput(&mut input, 1); // updates the buttons currently being pressed
+                let bounds = self.balls[i].get_bounds();
+                let scorer = if bounds.left <= 0{ // Check to see if the ball is in the player's goal
+                    Some(Side::Opponent)
+                } else if bounds.right >= SCREEN_WIDTH as i32{ // Check to see if the ball is in the opponent's goal
+                    Some(Side::Player)
+                } else {
+                    None
+                };
+                if let Some(winner) = scorer.and_then(|side| self.score.add_point(side, &self.rules)){
+                    self.set_state(GameState::GameOver(winner));
+                    return;
+                }
+                let scored = scorer.is_some();
+                if scored && self.balls.len() > 1{ // Only the last ball in play gets served again
+                    self.balls.remove(i);
+                    continue;
+                } else if let Some(scorer) = scorer{
+                    self.serve_to = match self.serve_rule{
+                        ServeRule::Loser => scorer.other(),
+                        ServeRule::Alternate => self.serve_to.other(),
+                    };
+                    self.set_state(GameState::PointScored(POINT_DELAY));
+                    return;
                 }
+                i += 1;
+            }
+            self.spawn_balls();
+            self.move_paddles();
+        }
 
-                if current_tick > last_tick + self.ticks_per_update as u64 { // Time to update the screen :)
+        /// Keeps the balls and paddles where they are, so they are not drawn between their last two positions
+        /// while they wait.
+        fn hold_still(&mut self){
+            self.player.stay();
+            self.opponent.stay();
+            for ball in self.balls.iter_mut(){
+                ball.start_step();
+            }
+        }
 
-                    if menu_f{ // If the menu has been enabled
+        /// Moves both paddles, whether they are controlled by a human or the computer.
+        fn move_paddles(&mut self){
+            let tracked = self.tracked_ball(Side::Player);
+            Self::move_control(&mut self.player, &self.balls[tracked], &self.input, Buttons::UP, Buttons::DOWN);
+            let tracked = self.tracked_ball(Side::Opponent);
+            Self::move_control(&mut self.opponent, &self.balls[tracked], &self.input, Buttons::TRIANGLE, Buttons::CROSS);
+        }
 
-                        if !input.buttons.contains(CtrlButtons::DOWN){
-                            menu_df = false;
-                        }
+        /// Moves a paddle after `ball` if the computer controls it, or with the `up` and `down` buttons if a human does.
+        fn move_control(control: &mut PaddleControl, ball: &Ball, input: &InputState, up: Buttons, down: Buttons){
+            match control{
+                PaddleControl::Computer(opponent) => opponent.step_opponent(ball),
+                PaddleControl::Human(paddle) => Self::move_paddle(paddle, input, up, down),
+            }
+        }
 
-                        if !input.buttons.contains(CtrlButtons::UP){
-                            menu_uf = false;
-                        }
+        /// Takes every ball but one out of play and puts that one back in the middle of the screen.
+        fn reset_balls(&mut self){
+            self.balls.truncate(1);
+            let leftwards = self.serve_to == Side::Player;
+            self.balls[0].serve(SCREEN_WIDTH as i32/2, SCREEN_HEIGHT as i32/2, leftwards, &mut self.rng);
+            self.spawn_counter = 0;
+        }
 
-                        if !input.buttons.contains(CtrlButtons::CROSS){
-                            menu_sf = false;
-                        }
+        /// Returns the button that serves the ball by hand, if the one serving is a human and manual serves are on.
+        /// The side the ball heads away from serves it.
+        fn serve_button(&self) -> Option<Buttons>{
+            if !self.manual_serve{
+                return None;
+            }
+            let (server, button) = match self.serve_to.other(){
+                Side::Player => (&self.player, Buttons::LTRIGGER),
+                Side::Opponent => (&self.opponent, Buttons::RTRIGGER),
+            };
+            match server{
+                PaddleControl::Human(_) => Some(button),
+                PaddleControl::Computer(_) => None, // the computer waits for the countdown
+            }
+        }
 
-                        if input.buttons.contains(CtrlButtons::DOWN) && !menu_df{
-                            main_menu.move_down(&mut self.display);
-                            menu_df = true;
-                        }
+        /// Sets who the ball is served towards after a point.
+        pub fn set_serve_rule(&mut self, serve_rule: ServeRule){
+            self.serve_rule = serve_rule;
+        }
 
-                        if input.buttons.contains(CtrlButtons::UP) && !menu_uf{
-                            main_menu.move_up(&mut self.display);
-                            menu_uf = true;
-                        }
+        /// Sets whether a human serving has to press a button to serve instead of waiting for the countdown.
+        pub fn set_manual_serve(&mut self, manual_serve: bool){
+            self.manual_serve = manual_serve;
+        }
 
-                        if input.buttons.contains(CtrlButtons::CROSS) && !menu_sf{
-                            match main_menu.return_selected_index(){
-                                0 => { // Set difficulty
-
-                                },
-                                1 => { // Reset Score
-                                    menu_f = false;
-                                    menu_uf = false;
-                                    menu_df = false;
-                                    menu_sf = false;
-                                    self.score.player = 0;
-                                    self.score.opponent = 0;
-                                    main_menu.hide_menu(&mut self.display);
-                                },
-                                2 => { // Close Menu
-                                    menu_f = false;
-                                    menu_uf = false;
-                                    menu_df = false;
-                                    menu_sf = false;
-                                    main_menu.hide_menu(&mut self.display);
-                                },
-                                n => {
-                                    psp::dprintln!("An error has occurred while selecting something in the main menu");
-                                    psp::dprintln!("{} is not a valid index", n);
-                                    panic!("{} is not a valid index", n);
-                                }
-                            }
+        /// Moves a paddle controlled by a human, keeping it on the screen.
+        fn move_paddle(paddle: &mut Paddle, input: &InputState, up: Buttons, down: Buttons){
+            if input.held(up) && paddle.get_bounds().top > 0{
+                paddle.move_up()
+            } else if input.held(down) && paddle.get_bounds().bottom < SCREEN_HEIGHT as i32{
+                paddle.move_down()
+            } else {
+                paddle.stay()
+            }
+        }
 
-                        }
+        /// Hands the right paddle to a second player, or back to the computer.
+        pub fn set_two_players(&mut self, two_players: bool){
+            if two_players == matches!(self.opponent, PaddleControl::Human(_)){
+                return;
+            }
+            self.opponent = if two_players{
+                PaddleControl::Human(PaddleBuilder::new()
+                    .set_default_dimensions()
+                    .set_speed(PaddleMovementSpeed::Normal)
+                    .set_x(SCREEN_WIDTH as i32 - 15)
+                    .set_y(SCREEN_HEIGHT as i32/2)
+                    .build())
+            } else {
+                let mut opponent = Opponent::new(self.difficulty);
+                opponent.set_ai(self.ai);
+                PaddleControl::Computer(opponent)
+            };
+        }
 
-                        continue; // All other events will not execute as long as the window is not closed
+        /// Hands the left paddle to the computer playing at a difficulty with an AI, or back to the player with `None`.
+        pub fn set_left_computer(&mut self, computer: Option<(OpponentDifficulty, OpponentAi)>){
+            self.player = match computer{
+                Some((difficulty, ai)) => {
+                    let mut opponent = Opponent::on_side(Side::Player, difficulty);
+                    opponent.set_ai(ai);
+                    PaddleControl::Computer(opponent)
+                },
+                None => PaddleControl::Human(PaddleBuilder::new()
+                    .set_default_dimensions()
+                    .set_speed(PaddleMovementSpeed::Normal)
+                    .set_x(10)
+                    .set_y(SCREEN_HEIGHT as i32/2)
+                    .build()),
+            };
+        }
+
+        /// Switches between one and several balls being in play. Extra balls are taken out of play
+        /// when going back to one.
+        pub fn set_game_mode(&mut self, game_mode: GameMode){
+            self.game_mode = game_mode;
+            self.spawn_counter = 0;
+            if self.game_mode == GameMode::Classic{
+                self.balls.truncate(1);
+            }
+        }
+
+        /// Sets how many pixels per update balls are served at. Balls already in play keep their speed until they are served again.
+        pub fn set_ball_speed(&mut self, speed: f32){
+            self.ball_speed = speed;
+            for ball in &mut self.balls{
+                ball.set_serve_speed(speed);
+            }
+        }
+
+        /// Creates a ball in the middle of the screen heading in a random direction at `speed`.
+        fn new_ball(rng: &mut ChaCha20Rng, speed: f32) -> Ball{
+            Ball::new(SCREEN_WIDTH as i32/2, SCREEN_HEIGHT as i32/2, 10, SERVE_CONE, rng)
+                .with_max_speed(MAX_BALL_SPEED)
+                .with_speed(speed)
+        }
+
+        /// Puts another ball into play if the game mode calls for one.
+        fn spawn_balls(&mut self){
+            let due = match self.game_mode{
+                GameMode::Classic => false,
+                GameMode::MultiBall(BallSpawn::Timer(ms)) => {
+                    self.spawn_counter += self.update_interval;
+                    self.spawn_counter >= ms as u64 * 1000
+                },
+                GameMode::MultiBall(BallSpawn::PaddleHits(hits)) => self.spawn_counter >= hits as u64,
+            };
+            if due && !self.balls.is_full(){
+                self.balls.push(Self::new_ball(&mut self.rng, self.ball_speed));
+                self.spawn_counter = 0;
+            }
+        }
+
+        /// Returns the index of the ball the computer on `side` should go after: the closest one heading towards it, if any.
+        fn tracked_ball(&self, side: Side) -> usize{
+            let balls = self.balls.iter().enumerate();
+            let tracked = match side{
+                Side::Player => balls
+                    .filter(|(_, ball)| ball.get_velocity().x < 0.0)
+                    .min_by_key(|(_, ball)| ball.get_bounds().left),
+                Side::Opponent => balls
+                    .filter(|(_, ball)| ball.get_velocity().x > 0.0)
+                    .max_by_key(|(_, ball)| ball.get_bounds().right),
+            };
+            tracked.map_or(0, |(i, _)| i)
+        }
+
+        /// Moves a ball, bouncing it off anything in its way. Returns whether it hit a paddle.
+        fn step_ball(&mut self, index: usize) -> bool{
+            let mut hit_paddle = false;
+            self.balls[index].start_step();
+            let mut remaining = 1.0; // how much of this update's movement is left
+            for _ in 0..MAX_BOUNCES_PER_STEP{
+                let ball = &self.balls[index];
+                let motion = ball.get_velocity() * remaining;
+                let (hit, surface) = match self.first_hit(ball, motion){
+                    Some(first) => first,
+                    None => {
+                        self.balls[index].move_ball(motion);
+                        break;
                     }
+                };
+                let ball = &mut self.balls[index];
+                ball.move_ball(motion * hit.time); // Move the ball up against what it hit
+                remaining *= 1.0 - hit.time;
 
-                    if input.buttons.contains(CtrlButtons::START){ // start button is pressed. Menu should appear (maybe)
-                        menu_f = true;
-                        main_menu.show_menu(&mut self.display);
-                        continue;
+                if hit.normal.y != 0.0{
+                    ball.flip_direction(BallAxes::Vertical); // Makes the ball bounce off the walls and the ends of the paddles
+                } else {
+                    match surface{
+                        Surface::Player => {
+                            let paddle = self.player.get_paddle();
+                            let offset = paddle.hit_offset(ball.get_position().y);
+                            ball.deflect(offset, paddle.get_velocity() as f32); // Makes the ball bounce off the player's paddle, steered by where it hit
+                            hit_paddle = true;
+                        },
+                        Surface::Opponent => {
+                            let paddle = self.opponent.get_paddle();
+                            let offset = paddle.hit_offset(ball.get_position().y);
+                            ball.deflect(offset, paddle.get_velocity() as f32); // Makes the ball bounce of the opponent's paddle, steered by where it hit
+                            hit_paddle = true;
+                        },
+                        Surface::Wall => ball.flip_direction(BallAxes::Horizontal),
                     }
+                }
+            }
+            hit_paddle
+        }
+
+        /// Finds the first thing a ball would hit moving by `motion`.
+        fn first_hit(&self, ball: &Ball, motion: Vec2) -> Option<(Hit, Surface)>{
+            let start = ball.get_position();
+            let radius = ball.get_radius();
+            let surfaces = [
+                (TOP_WALL, Surface::Wall),
+                (BOTTOM_WALL, Surface::Wall),
+                (Aabb::from(self.player.get_paddle().get_bounds()), Surface::Player),
+                (Aabb::from(self.opponent.get_paddle().get_bounds()), Surface::Opponent),
+            ];
+            surfaces.iter()
+                .filter_map(|&(rect, surface)| collision::sweep(start, motion, radius, rect).map(|hit| (hit, surface)))
+                .min_by(|a, b| a.0.time.partial_cmp(&b.0.time).unwrap_or(Ordering::Equal))
+        }
+
+        /// Freezes a match that is being played and shows the pause screen.
+        pub fn pause(&mut self){
+            if matches!(self.state, GameState::Serve(_) | GameState::Playing | GameState::PointScored(_)){
+                self.set_state(GameState::Paused);
+            }
+        }
+
+        /// Carries on with a paused match, closing any open menus.
+        pub fn resume(&mut self){
+            if self.state == GameState::Paused{
+                self.set_state(self.paused_state);
+            }
+        }
+
+        /// Starts a new match with the current settings.
+        pub fn start_match(&mut self){
+            self.score.reset();
+            self.serve_to = if self.rng.gen::<bool>() { Side::Player } else { Side::Opponent }; // the first serve goes either way
+            self.set_state(GameState::Serve(SERVE_COUNTDOWN));
+        }
+
+        /// Leaves the match for the title screen.
+        pub fn go_to_title(&mut self){
+            self.set_state(GameState::Title);
+        }
+
+        /// Sets how a match is won. A match that is already being played carries on under the new rules.
+        pub fn set_rules(&mut self, rules: MatchRules){
+            self.rules = rules;
+        }
+
+        /// Returns whether the game is being played, is paused or is over.
+        pub fn state(&self) -> GameState{
+            self.state
+        }
+
+        /// Draws the whole frame from scratch, with the playfield `alpha` of the way between the last two updates,
+        /// and shows it once it is done.
+        pub fn render(&mut self, alpha: f32){
+            let _ = self.display.clear(Rgb888::BLACK);
+            match self.state{
+                GameState::Title => self.draw_title_screen(),
+                GameState::Serve(_) | GameState::Playing | GameState::PointScored(_) => self.draw_playfield(alpha),
+                GameState::Paused => {
+                    self.draw_playfield(alpha);
+                    self.draw_pause_screen();
+                },
+                GameState::GameOver(winner) => self.draw_game_over_screen(winner),
+            }
+            if let Some(&menu) = self.menu_stack.last(){
+                self.draw_menu(menu);
+            }
+            self.display.present();
+        }
+
+        /// Draws the balls, both paddles and the score.
+        fn draw_playfield(&mut self, alpha: f32){
+            self.draw_score();
+            if let GameState::Serve(remaining) = self.state{
+                self.draw_serve_prompt(remaining);
+            }
+            self.player.draw(&mut self.display, alpha);
+            self.opponent.draw(&mut self.display, alpha);
+            for ball in &self.balls{
+                ball.draw(&mut self.display, alpha); // the balls go over everything else
+            }
+        }
+
+        /// Returns the current score.
+        pub fn score(&self) -> &Score{
+            &self.score
+        }
+
+        /// Returns the balls in play.
+        pub fn balls(&self) -> &[Ball]{
+            &self.balls
+        }
 
-                    // Time to check the ball
-                    if self.ball.get_bounds().top <= 0{
-                        self.ball.flip_direction(BallAxes::Vertical); // Makes the ball bounce off the top of the screen
-                    } else if self.ball.get_bounds().bottom >= psp::SCREEN_HEIGHT as i32{
-                        self.ball.flip_direction(BallAxes::Vertical); // Makes the ball bounce off the bottom of the screen
-                    } else if self.player.contains(self.ball.get_bounds().left, self.ball.get_bounds().bottom, true) ||
-                        self.player.contains(self.ball.get_bounds().left, self.ball.get_bounds().top, true){ // Check to see if the ball hit the player's paddle
-                        self.ball.flip_direction(BallAxes::Horizontal); // Makes the ball bounce off the player's paddle
-                        self.player.move_up(&mut self.display);
-                        self.player.move_down(&mut self.display);
-                    } else if self.opponent.contains(self.ball.get_bounds().right, self.ball.get_bounds().bottom) ||
-                        self.opponent.contains(self.ball.get_bounds().right, self.ball.get_bounds().top){ // Check to see if the ball hit the opponent's paddle
-                        self.ball.flip_direction(BallAxes::Horizontal); // Makes the ball bounce of the opponent's paddle
-                    } else if self.ball.get_bounds().left <= 0{ // Check to see if the ball is in the player's goal
-                        self.score.opponent += 1;
-                        self.ball.blacken(&mut self.display);
-                        self.ball = Ball::new(psp::SCREEN_WIDTH as i32/2, psp::SCREEN_HEIGHT as i32/2, 10, 3, &mut self.rng);
-                        opponent_score_buf.clear();
-                        write!(&mut opponent_score_buf, "{}", self.score.opponent);
-
-                        opponent_score = Text::new(&opponent_score_buf, Point::new(psp::SCREEN_WIDTH as i32 - 50, 5))
-                            .into_styled(score_style); // updates the opponent's score;
-
-                        //Self::wait(1000); //wait for a second before moving the ball again
-                    } else if self.ball.get_bounds().right >= psp::SCREEN_WIDTH as i32{ // Check to see if the ball is in the opponent's goal
-                        self.score.player += 1;
-                        self.ball.blacken(&mut self.display);
-                        self.ball = Ball::new(psp::SCREEN_WIDTH as i32/2, psp::SCREEN_HEIGHT as i32/2, 10, 3, &mut self.rng);
-                        player_score_buf.clear();
-                        write!(&mut player_score_buf, "{}", self.score.player);
-
-                        player_score = Text::new(&player_score_buf, Point::new(5, 5))
-                            .into_styled(score_style); // updates the player's score;
-
-                        //Self::wait(1000); //wait for a second before moving the ball again
+        /// Gets an immutable reference to the paddle on `side` of the screen.
+        pub fn paddle(&self, side: Side) -> &Paddle{
+            match side{
+                Side::Player => self.player.get_paddle(),
+                Side::Opponent => self.opponent.get_paddle(),
+            }
+        }
+
+        /// Returns the display the game is drawn to.
+        pub fn display(&self) -> &D{
+            &self.display
+        }
+
+        /// Handles the buttons while a menu is open. Circle goes back to the previous menu.
+        fn step_menu(&mut self){
+            let menu = match self.menu_stack.last(){
+                Some(&menu) => menu,
+                None => return,
+            };
+
+            if self.input.pressed(Buttons::DOWN){
+                match menu{
+                    MenuId::Main => self.main_menu.move_down(),
+                    MenuId::Difficulty => self.difficulty_menu.move_down(),
+                    MenuId::Title => self.title_menu.move_down(),
+                    MenuId::GameOver => self.game_over_menu.move_down(),
+                }
+            }
+
+            if self.input.pressed(Buttons::UP){
+                match menu{
+                    MenuId::Main => self.main_menu.move_up(),
+                    MenuId::Difficulty => self.difficulty_menu.move_up(),
+                    MenuId::Title => self.title_menu.move_up(),
+                    MenuId::GameOver => self.game_over_menu.move_up(),
+                }
+            }
+
+            if let MenuId::Main = menu{ // only the main menu has items with values to change
+                let changed = if self.input.pressed(Buttons::LEFT){
+                    self.main_menu.move_left()
+                } else if self.input.pressed(Buttons::RIGHT){
+                    self.main_menu.move_right()
+                } else {
+                    false
+                };
+                if changed{
+                    self.change_main_menu();
+                }
+            }
+
+            if self.input.pressed(Buttons::CIRCLE) && menu != MenuId::Title && menu != MenuId::GameOver{ // these menus stay until something is picked
+                self.pop_menu();
+            } else if self.input.pressed(Buttons::CROSS){
+                match menu{
+                    MenuId::Main => self.select_main_menu(),
+                    MenuId::Difficulty => self.select_difficulty_menu(),
+                    MenuId::Title => self.select_title_menu(),
+                    MenuId::GameOver => self.select_game_over_menu(),
+                }
+            }
+        }
+
+        /// Does what the selected item of the main menu says.
+        fn select_main_menu(&mut self){
+            match self.main_menu.selected_action(){
+                MainMenuAction::SetDifficulty => {
+                    self.push_menu(MenuId::Difficulty);
+                },
+                MainMenuAction::TwoPlayers | MainMenuAction::WinByTwo | MainMenuAction::ManualServe => {
+                    if self.main_menu.press(){
+                        self.change_main_menu();
+                    }
+                },
+                MainMenuAction::OpponentAi(_) | MainMenuAction::MultiBall(_) | MainMenuAction::BallSpeed | MainMenuAction::PointsToWin
+                | MainMenuAction::BestOf(_) | MainMenuAction::ServeTo(_) => {}, // these are changed with left and right
+                MainMenuAction::ResetScore => {
+                    self.close_menu();
+                    self.score.reset();
+                },
+                MainMenuAction::Resume => {
+                    if self.state == GameState::Paused{
+                        self.resume();
+                    } else {
+                        self.pop_menu(); // back to the title or game over menu
                     }
+                },
+            }
+        }
+
+        /// Applies the value of the selected item of the main menu after it was changed.
+        fn change_main_menu(&mut self){
+            let value = self.main_menu.selected_value();
+            match self.main_menu.selected_action(){
+                MainMenuAction::OpponentAi(ai) => self.set_opponent_ai(ai),
+                MainMenuAction::MultiBall(mode) => self.set_game_mode(mode),
+                MainMenuAction::TwoPlayers => self.set_two_players(value != 0),
+                MainMenuAction::BallSpeed => self.set_ball_speed(value as f32),
+                MainMenuAction::PointsToWin => self.set_rules(MatchRules {points_to_win: value as u32, ..self.rules}),
+                MainMenuAction::WinByTwo => self.set_rules(MatchRules {win_by_two: value != 0, ..self.rules}),
+                MainMenuAction::BestOf(best_of) => self.set_rules(MatchRules {best_of, ..self.rules}),
+                MainMenuAction::ServeTo(rule) => self.set_serve_rule(rule),
+                MainMenuAction::ManualServe => self.set_manual_serve(value != 0),
+                MainMenuAction::SetDifficulty | MainMenuAction::ResetScore | MainMenuAction::Resume => {}, // buttons hold no value
+            }
+        }
+
+        /// Does what the selected item of the game over menu says.
+        fn select_game_over_menu(&mut self){
+            match self.game_over_menu.selected_action(){
+                GameOverAction::Rematch => self.start_match(),
+                GameOverAction::Settings => self.push_menu(MenuId::Main),
+                GameOverAction::Title => self.go_to_title(),
+            }
+        }
+
+        /// Does what the selected item of the title screen's menu says.
+        fn select_title_menu(&mut self){
+            match self.title_menu.selected_action(){
+                TitleAction::OnePlayer => {
+                    self.set_two_players(false);
+                    self.start_match();
+                },
+                TitleAction::TwoPlayers => {
+                    self.set_two_players(true);
+                    self.start_match();
+                },
+                TitleAction::Settings => self.push_menu(MenuId::Main),
+            }
+        }
+
+        /// Sets the difficulty picked in the difficulty menu.
+        fn select_difficulty_menu(&mut self){
+            let difficulty = self.difficulty_menu.selected_action();
+            self.pop_menu(); // back to the menu it was opened from
+            self.set_difficulty(difficulty);
+        }
+
+        /// Changes how hard the computer opponent is, keeping the score.
+        pub fn set_difficulty(&mut self, difficulty: OpponentDifficulty){
+            self.difficulty = difficulty;
+            if let PaddleControl::Computer(opponent) = &mut self.opponent{
+                opponent.set_difficulty(difficulty);
+            }
+        }
+
+        /// Changes how the computer opponent decides where to move its paddle.
+        pub fn set_opponent_ai(&mut self, ai: OpponentAi){
+            self.ai = ai;
+            if let PaddleControl::Computer(opponent) = &mut self.opponent{
+                opponent.set_ai(ai);
+            }
+        }
+
+        /// Opens a menu on top of any that are already open.
+        fn push_menu(&mut self, menu: MenuId){
+            if self.menu_stack.try_push(menu).is_err(){
+                return;
+            }
+            if menu == MenuId::Difficulty{
+                self.difficulty_menu.select_action(self.difficulty); // opens on the current difficulty
+            } else if menu == MenuId::Main{
+                // The settings might have been changed since the menu was last open
+                self.main_menu.pick(MainMenuAction::MultiBall(self.game_mode));
+                self.main_menu.pick(MainMenuAction::OpponentAi(self.ai));
+                self.main_menu.set_value(MainMenuAction::TwoPlayers, matches!(self.opponent, PaddleControl::Human(_)) as i32);
+                self.main_menu.set_value(MainMenuAction::BallSpeed, self.ball_speed as i32);
+                self.main_menu.set_value(MainMenuAction::PointsToWin, self.rules.points_to_win as i32);
+                self.main_menu.set_value(MainMenuAction::WinByTwo, self.rules.win_by_two as i32);
+                self.main_menu.pick(MainMenuAction::BestOf(self.rules.best_of));
+                self.main_menu.pick(MainMenuAction::ServeTo(self.serve_rule));
+                self.main_menu.set_value(MainMenuAction::ManualServe, self.manual_serve as i32);
+            }
+        }
+
+        /// Closes the menu being shown, going back to the one under it if there is one.
+        fn pop_menu(&mut self){
+            if let Some(menu) = self.menu_stack.pop(){
+                self.reset_menu(menu);
+            }
+        }
+
+        /// Closes every open menu.
+        fn close_menu(&mut self){
+            while let Some(menu) = self.menu_stack.pop(){
+                self.reset_menu(menu);
+            }
+        }
+
+        fn draw_menu(&mut self, menu: MenuId){
+            match menu{
+                MenuId::Main => self.main_menu.draw(&mut self.display),
+                MenuId::Difficulty => self.difficulty_menu.draw(&mut self.display),
+                MenuId::Title => self.title_menu.draw(&mut self.display),
+                MenuId::GameOver => self.game_over_menu.draw(&mut self.display),
+            }
+        }
+
+        fn reset_menu(&mut self, menu: MenuId){
+            match menu{
+                MenuId::Main => self.main_menu.reset(),
+                MenuId::Difficulty => self.difficulty_menu.reset(),
+                MenuId::Title => self.title_menu.reset(),
+                MenuId::GameOver => self.game_over_menu.reset(),
+            }
+        }
+
+        /// Draws a box in the middle of the screen saying the game is paused and which buttons to press.
+        fn draw_pause_screen(&mut self){
+            let center = Point::new(SCREEN_WIDTH as i32/2, SCREEN_HEIGHT as i32/2);
+            let _ = Rectangle::new(center - Point::new(100, 30), center + Point::new(100, 30))
+                .into_styled(PrimitiveStyleBuilder::new()
+                    .fill_color(Rgb888::BLACK)
+                    .stroke_color(Rgb888::WHITE)
+                    .stroke_width(1)
+                    .build())
+                .draw(&mut self.display);
+
+            let title = "Paused";
+            let title_style = TextStyleBuilder::new(Font12x16)
+                .text_color(Rgb888::YELLOW)
+                .build();
+            let _ = Text::new(title, center - Point::new(title.len() as i32*6, 22))
+                .into_styled(title_style)
+                .draw(&mut self.display);
+
+            let hint = "Start: Resume  Select: Menu";
+            let _ = Text::new(hint, center - Point::new(hint.len() as i32*3, -6))
+                .into_styled(self.score_style)
+                .draw(&mut self.display);
+        }
+
+        /// Draws the seconds left before the ball is served above it, or which button serves it when it is served by hand.
+        #[allow(clippy::manual_div_ceil)] // div_ceil is newer than the compiler the PSP needs
+        fn draw_serve_prompt(&mut self, remaining: u64){
+            let mut prompt_buf = ArrayString::<[_;24]>::new();
+            let center_x = SCREEN_WIDTH as i32/2;
+            let top = SCREEN_HEIGHT as i32/2 - 40;
+            if let Some(button) = self.serve_button(){
+                let _ = write!(&mut prompt_buf, "Press {} to serve", if button == Buttons::LTRIGGER { "L" } else { "R" });
+                let _ = Text::new(&prompt_buf, Point::new(center_x - prompt_buf.len() as i32*3, top))
+                    .into_styled(self.score_style)
+                    .draw(&mut self.display);
+            } else {
+                let _ = write!(&mut prompt_buf, "{}", (remaining + 999_999) / 1_000_000); // whole seconds, rounded up
+                let countdown_style = TextStyleBuilder::new(Font12x16)
+                    .text_color(Rgb888::YELLOW)
+                    .background_color(Rgb888::BLACK)
+                    .build();
+                let _ = Text::new(&prompt_buf, Point::new(center_x - prompt_buf.len() as i32*6, top))
+                    .into_styled(countdown_style)
+                    .draw(&mut self.display);
+            }
+        }
+
+        /// Draws the name of the game above the title screen's menu.
+        fn draw_title_screen(&mut self){
+            let title = "PSP Pong";
+            let title_style = TextStyleBuilder::new(Font12x16)
+                .text_color(Rgb888::YELLOW)
+                .build();
+            let _ = Text::new(title, Point::new(SCREEN_WIDTH as i32/2 - title.len() as i32*6, 50))
+                .into_styled(title_style)
+                .draw(&mut self.display);
+
+            let hint = "Cross: Select  Start: Pause";
+            let _ = Text::new(hint, Point::new(SCREEN_WIDTH as i32/2 - hint.len() as i32*3, 80))
+                .into_styled(self.score_style)
+                .draw(&mut self.display);
+        }
+
+        /// Draws who won the match along with the final score.
+        fn draw_game_over_screen(&mut self, winner: Side){
+            let two_players = matches!(self.opponent, PaddleControl::Human(_));
+            let title = match (winner, two_players){
+                (Side::Player, false) => "You Win!",
+                (Side::Opponent, false) => "You Lose!",
+                (Side::Player, true) => "Left Player Wins!",
+                (Side::Opponent, true) => "Right Player Wins!",
+            };
+            let title_style = TextStyleBuilder::new(Font12x16)
+                .text_color(if winner == Side::Player || two_players { Rgb888::YELLOW } else { Rgb888::RED })
+                .build();
+            let center_x = SCREEN_WIDTH as i32/2;
+            let _ = Text::new(title, Point::new(center_x - title.len() as i32*6, 40))
+                .into_styled(title_style)
+                .draw(&mut self.display);
 
+            let mut score_buf = ArrayString::<[_;24]>::new();
+            let _ = write!(&mut score_buf, "{} - {}", self.score.player, self.score.opponent);
+            let _ = Text::new(&score_buf, Point::new(center_x - score_buf.len() as i32*6, 70))
+                .into_styled(title_style)
+                .draw(&mut self.display); // the points of the last set
 
-                    self.ball.step_direction(&mut self.display); // Move the ball in the direction it is heading.
+            if self.rules.best_of > 1{
+                score_buf.clear();
+                let _ = write!(&mut score_buf, "Sets {} - {}", self.score.player_sets, self.score.opponent_sets);
+                let _ = Text::new(&score_buf, Point::new(center_x - score_buf.len() as i32*3, 95))
+                    .into_styled(self.score_style)
+                    .draw(&mut self.display);
+            }
+        }
+
+        /// Draws both the player's and the opponent's score.
+        /// The sets each side has won are shown in brackets when the match lasts more than one set.
+        fn draw_score(&mut self){
+            let mut score_buf = ArrayString::<[_;12]>::new();
+
+            let _ = write!(&mut score_buf, "{}", self.score.player);
+            if self.rules.best_of > 1{
+                let _ = write!(&mut score_buf, " ({})", self.score.player_sets);
+            }
+            let _ = Text::new(&score_buf, Point::new(5, 5))
+                .into_styled(self.score_style)
+                .draw(&mut self.display); // draw the player's score
+
+            score_buf.clear();
+            let _ = write!(&mut score_buf, "{}", self.score.opponent);
+            if self.rules.best_of > 1{
+                let _ = write!(&mut score_buf, " ({})", self.score.opponent_sets);
+            }
+            let _ = Text::new(&score_buf, Point::new(SCREEN_WIDTH as i32 - 50, 5))
+                .into_styled(self.score_style)
+                .draw(&mut self.display); //draw the opponent's score
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::pong_controller::display::NullDisplay;
+        use crate::pong_controller::clock::ManualClock;
+
+        /// Returns a game with its only ball at `x`, `y` moving by `velocity` each update. The left paddle
+        /// covers x 10 to 20 and y 136 to 176.
+        fn game_with_ball(x: i32, y: i32, velocity: Vec2) -> PongController<NullDisplay>{
+            let mut pong = PongController::new(NullDisplay, 20, 1);
+            pong.balls[0] = Ball::new(x, y, 10, 0.0, &mut pong.rng).with_velocity(velocity);
+            pong
+        }
+
+        #[test]
+        #[should_panic]
+        fn updating_zero_times_a_second_is_rejected(){
+            PongController::new(NullDisplay, 0, 1);
+        }
+
+        #[test]
+        fn the_serve_countdown_lasts_three_seconds_on_the_clock(){
+            let clock = ManualClock::new(0);
+            let mut pong = PongController::new(NullDisplay, 20, 1);
+            let mut timestep = FixedTimestep::new(pong.update_interval, clock.now(), MAX_CATCH_UP_STEPS);
+            pong.start_match();
+            while let GameState::Serve(_) = pong.state(){
+                clock.advance(10_000);
+                for _ in 0..timestep.advance(clock.now()){
+                    pong.step(Buttons::empty());
+                }
+            }
+            assert_eq!(pong.state(), GameState::Playing);
+            assert_eq!(clock.now(), SERVE_COUNTDOWN);
+        }
+
+        /// Presses a button and lets go of it on the next update.
+        fn press(pong: &mut PongController<NullDisplay>, button: Buttons){
+            pong.step(button);
+            pong.step(Buttons::empty());
+        }
+
+        #[test]
+        fn picking_a_difficulty_goes_back_to_the_settings(){
+            let mut pong = PongController::new(NullDisplay, 20, 1);
+            pong.start_match();
+            pong.pause();
+            press(&mut pong, Buttons::SELECT);
+            press(&mut pong, Buttons::CROSS); // Set Difficulty
+            assert!(pong.menu_stack.as_slice() == [MenuId::Main, MenuId::Difficulty]);
+            assert!(pong.difficulty_menu.selected_action() == OpponentDifficulty::Hard, "the current difficulty is highlighted");
+
+            press(&mut pong, Buttons::UP);
+            press(&mut pong, Buttons::CROSS);
+            assert!(pong.difficulty == OpponentDifficulty::Normal);
+            assert!(pong.menu_stack.as_slice() == [MenuId::Main]);
+            assert_eq!(pong.state(), GameState::Paused);
+
+            press(&mut pong, Buttons::CROSS); // Set Difficulty again
+            assert!(pong.difficulty_menu.selected_action() == OpponentDifficulty::Normal);
+        }
+
+        #[test]
+        fn pickers_in_the_settings_apply_the_picked_value(){
+            let mut pong = PongController::new(NullDisplay, 20, 1);
+            pong.start_match();
+            pong.pause();
+            press(&mut pong, Buttons::SELECT);
+            for _ in 0..7{
+                press(&mut pong, Buttons::DOWN); // Best Of
+            }
+            press(&mut pong, Buttons::RIGHT);
+            assert_eq!(pong.rules.best_of, 3);
+
+            pong.pop_menu();
+            pong.set_rules(MatchRules {best_of: 7, ..pong.rules});
+            pong.set_serve_rule(ServeRule::Alternate);
+            pong.push_menu(MenuId::Main);
+            assert!(pong.main_menu.selected_action() == MainMenuAction::SetDifficulty);
+            for _ in 0..7{
+                pong.main_menu.move_down();
+            }
+            assert!(pong.main_menu.selected_action() == MainMenuAction::BestOf(7), "the menu shows the current rules");
+            pong.main_menu.move_down();
+            assert!(pong.main_menu.selected_action() == MainMenuAction::ServeTo(ServeRule::Alternate));
+        }
+
+        #[test]
+        fn picking_a_difficulty_after_a_match_keeps_the_game_over_menu(){
+            let mut pong = PongController::new(NullDisplay, 20, 1);
+            pong.set_state(GameState::GameOver(Side::Player));
+            press(&mut pong, Buttons::DOWN);
+            press(&mut pong, Buttons::CROSS); // Settings
+            press(&mut pong, Buttons::CROSS); // Set Difficulty
+            press(&mut pong, Buttons::CROSS);
+            assert!(pong.menu_stack.as_slice() == [MenuId::GameOver, MenuId::Main]);
+        }
+
+        #[test]
+        fn landing_on_the_end_of_a_paddle_bounces_the_ball_back_up(){
+            let mut pong = game_with_ball(15, 118, Vec2::new(0.0, 10.0));
+            assert!(!pong.step_ball(0), "the end of the paddle is not a hit with its face");
+            let velocity = pong.balls[0].get_velocity();
+            assert_eq!((velocity.x, velocity.y), (0.0, -10.0));
+            assert!(pong.balls[0].get_bounds().bottom <= 136);
+        }
+
+        #[test]
+        fn hitting_a_paddle_near_its_edge_sends_the_ball_off_at_an_angle(){
+            let mut pong = game_with_ball(38, 138, Vec2::new(-10.0, 0.0));
+            assert!(pong.step_ball(0));
+            let velocity = pong.balls[0].get_velocity();
+            assert!(velocity.x > 0.0, "the ball goes back the way it came");
+            assert!(velocity.y < 0.0, "hitting the top half of the paddle sends it upwards");
+            assert!(pong.balls[0].get_bounds().left >= 20);
+        }
 
-                    if input.buttons.contains(CtrlButtons::UP) && self.player.get_bounds().top > 0{
-                        self.player.move_up( &mut self.display)
-                    } else if input.buttons.contains(CtrlButtons::DOWN) && self.player.get_bounds().bottom < psp::SCREEN_HEIGHT as i32{
-                        self.player.move_down(&mut self.display)
+        /// Returns a game in the middle of a rally with its only ball at `x`, `y` moving by `velocity` each update.
+        fn rally_with_ball(x: i32, y: i32, velocity: Vec2) -> PongController<NullDisplay>{
+            let mut pong = game_with_ball(x, y, velocity);
+            pong.state = GameState::Playing;
+            pong
+        }
+
+        #[test]
+        fn a_timed_spawn_comes_after_its_time_is_up(){
+            let mut pong = rally_with_ball(240, 60, Vec2::new(1.0, 0.0));
+            pong.set_game_mode(GameMode::MultiBall(BallSpawn::Timer(1000))); // 20 updates at 20 a second
+            for _ in 0..19{
+                pong.step(Buttons::empty());
+            }
+            assert_eq!(pong.balls().len(), 1);
+            pong.step(Buttons::empty());
+            assert_eq!(pong.balls().len(), 2);
+        }
+
+        #[test]
+        fn a_spawn_comes_after_enough_paddle_hits(){
+            // Heads straight at the middle of the left paddle, and then of the right one, which stays put
+            let mut pong = rally_with_ball(40, 156, Vec2::new(-10.0, 0.0));
+            pong.set_game_mode(GameMode::MultiBall(BallSpawn::PaddleHits(2)));
+            let mut hits = 0;
+            for _ in 0..100{
+                let heading_left = pong.balls[0].get_velocity().x < 0.0;
+                pong.step(Buttons::empty());
+                if (pong.balls[0].get_velocity().x < 0.0) != heading_left{
+                    hits += 1;
+                    assert_eq!(pong.balls().len(), hits, "a ball is spawned on the second hit and not before");
+                    if hits == 2{
+                        break;
                     }
-                    self.opponent.step_opponent(self.ball.get_bounds(), &mut self.display);
-                    player_score.draw(&mut self.display); // draw the player's score
-                    opponent_score.draw(&mut self.display); //draw the opponent's score
                 }
+            }
+            assert_eq!(hits, 2);
+            assert_eq!(pong.state(), GameState::Playing);
+        }
+
+        #[test]
+        fn the_computer_goes_after_the_closest_ball_heading_its_way(){
+            let mut pong = rally_with_ball(440, 60, Vec2::new(-4.0, 0.0)); // closest, but heading away
+            for &x in &[300, 400, 200]{
+                let ball = Ball::new(x, 100, 10, 0.0, &mut pong.rng).with_velocity(Vec2::new(4.0, 0.0));
+                pong.balls.push(ball);
+            }
+            assert_eq!(pong.tracked_ball(Side::Opponent), 2);
+            assert_eq!(pong.tracked_ball(Side::Player), 0);
+        }
+
+        #[test]
+        fn no_ball_is_spawned_once_the_most_are_in_play(){
+            let mut pong = rally_with_ball(240, 60, Vec2::new(1.0, 0.0));
+            pong.set_game_mode(GameMode::MultiBall(BallSpawn::Timer(50))); // due every update
+            for _ in 1..MAX_BALLS{
+                let ball = Ball::new(240, 100, 10, 0.0, &mut pong.rng).with_velocity(Vec2::new(1.0, 0.0));
+                pong.balls.push(ball);
+            }
+            for _ in 0..5{
+                pong.step(Buttons::empty());
+                assert_eq!(pong.balls().len(), MAX_BALLS);
+            }
+        }
+
+        #[test]
+        fn a_ball_scoring_while_others_are_in_play_is_taken_out(){
+            let mut pong = rally_with_ball(15, 40, Vec2::new(-10.0, 0.0)); // above the left paddle, about to go in
+            pong.set_game_mode(GameMode::MultiBall(BallSpawn::Timer(60_000)));
+            let other = Ball::new(240, 200, 10, 0.0, &mut pong.rng).with_velocity(Vec2::new(1.0, 0.0));
+            pong.balls.push(other);
+
+            pong.step(Buttons::empty());
+            assert_eq!(pong.balls().len(), 1);
+            assert_eq!(pong.balls()[0].get_position().y, 200.0, "the ball still in play is kept");
+            assert_eq!(pong.score().opponent, 1);
+            assert_eq!(pong.state(), GameState::Playing, "the rally goes on");
+        }
 
+        #[test]
+        fn only_the_last_ball_scoring_ends_the_rally(){
+            let mut pong = rally_with_ball(15, 40, Vec2::new(-10.0, 0.0));
+            pong.set_game_mode(GameMode::MultiBall(BallSpawn::Timer(60_000)));
+            pong.step(Buttons::empty());
+            assert!(matches!(pong.state(), GameState::PointScored(_)));
+            assert_eq!(pong.score().opponent, 1);
+
+            while let GameState::PointScored(_) = pong.state(){
+                pong.step(Buttons::empty());
             }
+            assert!(matches!(pong.state(), GameState::Serve(_)));
+            let ball = pong.balls()[0].get_position();
+            assert_eq!((ball.x, ball.y), (SCREEN_WIDTH as f32/2.0, SCREEN_HEIGHT as f32/2.0), "the ball is served from the middle");
         }
 
-        /// Suspend operations for N milliseconds
-        fn wait(ms: u32){
-            let mut last = 0;
-            unsafe {
-                getTick(&mut last);
+        /// Lets the ball into `side`'s goal and runs the game until the next serve.
+        fn concede(pong: &mut PongController<NullDisplay>, side: Side){
+            let (x, velocity) = match side{ // above the paddles, so nothing is in the way
+                Side::Player => (15, Vec2::new(-10.0, 0.0)),
+                Side::Opponent => (462, Vec2::new(10.0, 0.0)),
+            };
+            pong.balls[0] = Ball::new(x, 40, 10, 0.0, &mut pong.rng).with_velocity(velocity);
+            pong.state = GameState::Playing;
+            pong.step(Buttons::empty());
+            assert!(matches!(pong.state(), GameState::PointScored(_)));
+            while let GameState::PointScored(_) = pong.state(){
+                pong.step(Buttons::empty());
             }
-            let until = last + ms as u64;
+            assert!(matches!(pong.state(), GameState::Serve(_)));
+        }
 
-            while last <= until{
-                unsafe {let _ = getTick(&mut last);}
+        /// Returns the side the ball is heading towards.
+        fn serving_towards(pong: &PongController<NullDisplay>) -> Side{
+            if pong.balls()[0].get_velocity().x < 0.0 { Side::Player } else { Side::Opponent }
+        }
+
+        #[test]
+        fn the_loser_of_a_point_is_served_to(){
+            let mut pong = PongController::new(NullDisplay, 20, 1);
+            pong.set_serve_rule(ServeRule::Loser);
+            for &side in &[Side::Player, Side::Player, Side::Opponent, Side::Player]{
+                concede(&mut pong, side);
+                assert_eq!(serving_towards(&pong), side);
             }
+        }
 
+        #[test]
+        fn alternating_serves_go_to_each_side_in_turn(){
+            let mut pong = PongController::new(NullDisplay, 20, 1);
+            pong.set_serve_rule(ServeRule::Alternate);
+            let mut serves = [Side::Player; 4];
+            for serve in serves.iter_mut(){
+                concede(&mut pong, Side::Player); // whoever lets the points in
+                *serve = serving_towards(&pong);
+            }
+            assert_eq!(serves[0], serves[2]);
+            assert_eq!(serves[1], serves[3]);
+            assert_ne!(serves[0], serves[1]);
         }
 
+        #[test]
+        fn a_human_serving_by_hand_waits_for_their_button(){
+            let mut pong = PongController::new(NullDisplay, 20, 1);
+            pong.set_manual_serve(true);
+            pong.serve_to = Side::Opponent; // served by the player on the left, with L
+            pong.set_state(GameState::Serve(SERVE_COUNTDOWN));
+            for _ in 0..100{ // well past the countdown
+                pong.step(Buttons::empty());
+            }
+            press(&mut pong, Buttons::RTRIGGER);
+            assert!(matches!(pong.state(), GameState::Serve(_)), "only the server's button serves");
+            pong.step(Buttons::LTRIGGER);
+            assert_eq!(pong.state(), GameState::Playing);
+        }
 
+        #[test]
+        fn the_computer_serving_by_hand_still_counts_down(){
+            let mut pong = PongController::new(NullDisplay, 20, 1);
+            pong.set_manual_serve(true);
+            pong.serve_to = Side::Player; // served by the computer on the right
+            pong.set_state(GameState::Serve(SERVE_COUNTDOWN));
+            for _ in 1..60{
+                pong.step(Buttons::empty());
+            }
+            assert!(matches!(pong.state(), GameState::Serve(_)));
+            pong.step(Buttons::empty());
+            assert_eq!(pong.state(), GameState::Playing);
+        }
+
+        #[test]
+        fn a_second_player_moves_the_right_paddle_with_triangle_and_cross(){
+            let mut pong = rally_with_ball(400, 40, Vec2::new(1.0, 0.0)); // above the right paddle, which a computer would chase
+            pong.set_two_players(true);
+            let top = |pong: &PongController<NullDisplay>, side: Side| pong.paddle(side).get_bounds().top;
+            let (left, right) = (top(&pong, Side::Player), top(&pong, Side::Opponent));
+
+            pong.step(Buttons::empty());
+            assert_eq!(top(&pong, Side::Opponent), right, "nothing moves the right paddle on its own");
+            pong.step(Buttons::TRIANGLE);
+            assert!(top(&pong, Side::Opponent) < right);
+            pong.step(Buttons::CROSS);
+            pong.step(Buttons::CROSS);
+            assert!(top(&pong, Side::Opponent) > right);
+            assert_eq!(top(&pong, Side::Player), left, "the left paddle only answers to the d-pad");
+        }
+
+        #[test]
+        fn going_back_to_one_player_brings_back_the_computer_as_it_was_set_up(){
+            let mut pong = rally_with_ball(400, 40, Vec2::new(1.0, 0.0));
+            pong.set_two_players(true);
+            pong.set_difficulty(OpponentDifficulty::VeryHard);
+            pong.set_opponent_ai(OpponentAi::Predict);
+            pong.set_two_players(false);
+            match &pong.opponent{
+                PaddleControl::Computer(opponent) => {
+                    assert!(opponent.get_difficulty() == OpponentDifficulty::VeryHard);
+                    assert!(opponent.get_ai() == OpponentAi::Predict);
+                },
+                PaddleControl::Human(_) => panic!("the right paddle is still played by a human"),
+            }
+
+            let right = pong.paddle(Side::Opponent).get_bounds().top;
+            pong.step(Buttons::empty());
+            assert!(pong.paddle(Side::Opponent).get_bounds().top < right, "the computer goes after the ball again");
+        }
     }
-}
\ No newline at end of file
+}
@@ -1,15 +1,17 @@
 use embedded_graphics::style::{Styled, PrimitiveStyle, PrimitiveStyleBuilder};
 use embedded_graphics::primitives::Circle;
 use embedded_graphics::pixelcolor::Rgb888;
-use embedded_graphics::prelude::{RgbColor, Point, Primitive, Transform, Dimensions};
-use psp::embedded_graphics::Framebuffer;
+use embedded_graphics::prelude::{RgbColor, Point, Primitive, Dimensions};
+use embedded_graphics::DrawTarget;
 use embedded_graphics::drawable::Drawable;
-use rand;
-use rand::{RngCore, Error, SeedableRng, Rng};
-use rand::prelude::SmallRng;
-use rand_chacha::{ChaChaRng, ChaCha20Rng};
+use rand::Rng;
+use rand_chacha::ChaCha20Rng;
+use crate::pong_controller::vector::Vec2;
 
-static mut MOVEMENT_MAGNITUDE:i32 = 2; // defaults to 2
+const DEFAULT_SPEED: f32 = 2.0; // pixels moved per update
+const DEFAULT_MAX_SPEED: f32 = 12.0;
+const MAX_BOUNCE_ANGLE: f32 = core::f32::consts::FRAC_PI_3; // a ball hitting the very edge of a paddle leaves at 60 degrees
+const SPIN_PER_PIXEL: f32 = 0.05; // radians added to the bounce for every pixel the paddle moved that update
 
 pub struct BallBounds{
     pub top: i32,
@@ -20,9 +22,13 @@ pub struct BallBounds{
 
 pub struct Ball{
     circle:Styled<Circle, PrimitiveStyle<Rgb888>>,
-    background:Styled<Circle, PrimitiveStyle<Rgb888>>,
+    position: Vec2, // center of the ball
+    previous: Vec2, // center of the ball before the last move
     bounds: BallBounds,
-    direction: Point,
+    velocity: Vec2, // pixels moved per update
+    serve_cone: f32, // the most a serve can point away from straight across, in radians
+    serve_speed: f32, // speed the ball is served at
+    max_speed: f32, // speed the ball will never go faster than
     speed_counter: u32,
 }
 
@@ -33,95 +39,172 @@ pub enum BallAxes{
 
 
 impl Ball{
-    pub fn new(x:i32,y:i32,radius:u32, movement_magnitude:u32, rng: &mut ChaCha20Rng) -> Self{
+    /// Creates a ball at a point, served in a random direction at most `serve_cone` radians away from
+    /// straight across the screen.
+    pub fn new(x:i32,y:i32,radius:u32, serve_cone:f32, rng: &mut ChaCha20Rng) -> Self{
         let circle_style = PrimitiveStyleBuilder::new()
             .fill_color(Rgb888::WHITE)
             .build();
-        let background_style = PrimitiveStyleBuilder::new()
-            .fill_color(Rgb888::BLACK)
-            .build();
         let center = Point::new(x,y);
         let circle = Circle::new(center, radius).into_styled(circle_style);
-        let background = Circle::new(center, radius).into_styled(background_style);
         let bounds = BallBounds { top: y,
             bottom: circle.primitive.bottom_right().y,
             left: x,
             right: circle.primitive.bottom_right().x
         };
 
-        let direction= Self::gen_direction(rng);
-        unsafe {
-            MOVEMENT_MAGNITUDE = movement_magnitude as i32;
-        }
+        let velocity = Self::gen_direction(serve_cone, DEFAULT_SPEED, rng);
+        let position = Vec2::from(center);
+
+        Self {circle, position, previous: position, bounds, velocity, serve_cone,
+            serve_speed: DEFAULT_SPEED, max_speed: DEFAULT_MAX_SPEED, speed_counter: 0}
+    }
 
+    /// Sets the speed the ball is served at, including the serve it was created with.
+    pub fn with_speed(mut self, speed: f32) -> Self{
+        self.serve_speed = speed.min(self.max_speed);
+        self.set_speed(speed);
+        self
+    }
 
-        Self {circle, background, bounds, direction, speed_counter: 0}
+    /// Sets the direction and speed the ball moves in, limited to the ball's max speed.
+    pub fn with_velocity(mut self, velocity: Vec2) -> Self{
+        self.velocity = velocity;
+        self.set_speed(velocity.length());
+        self
     }
 
-    /// Returns a random direction.
-    fn gen_direction(rng: &mut ChaCha20Rng) -> Point{
-        let seed = rng.gen_range(0..=3);
-        unsafe {
-            match seed { // random number from 0-3 will appear, leading to the ball going in a random direction
-                0 => Point::new(-MOVEMENT_MAGNITUDE, -MOVEMENT_MAGNITUDE),
-                1 => Point::new(-MOVEMENT_MAGNITUDE, MOVEMENT_MAGNITUDE),
-                2 => Point::new(MOVEMENT_MAGNITUDE, -MOVEMENT_MAGNITUDE),
-                3 => Point::new(MOVEMENT_MAGNITUDE, MOVEMENT_MAGNITUDE),
-                _ => Point::new(0,0)
-            }
+    /// Sets the speed the ball can never go faster than.
+    pub fn with_max_speed(mut self, max_speed: f32) -> Self{
+        self.max_speed = max_speed;
+        self.serve_speed = self.serve_speed.min(max_speed);
+        self.set_speed(self.get_speed());
+        self
+    }
+
+    /// Returns a random velocity of `speed` heading left or right, at most `serve_cone` radians away from straight across.
+    fn gen_direction(serve_cone: f32, speed: f32, rng: &mut ChaCha20Rng) -> Vec2{
+        let angle = if serve_cone > 0.0 { rng.gen_range(-serve_cone..=serve_cone) } else { 0.0 };
+        let velocity = Vec2::from_angle(angle, speed);
+        if rng.gen::<bool>(){ // serves towards the left half of the time
+            Vec2::new(-velocity.x, velocity.y)
+        } else {
+            velocity
         }
     }
-    /// Blackens out the circle.
-    pub fn blacken(&mut self, disp: &mut Framebuffer) {
-        self.background.draw(disp);
+
+    /// Puts the ball back at a point with a new random direction and its starting speed.
+    pub fn reset(&mut self, x:i32, y:i32, rng: &mut ChaCha20Rng){
+        self.position = Vec2::from(Point::new(x,y));
+        self.previous = self.position; // the ball jumps there instead of travelling
+        self.velocity = Self::gen_direction(self.serve_cone, self.serve_speed, rng);
+        self.speed_counter = 0;
+        self.update_bounds();
     }
 
-    pub fn get_direction(&self) -> (i32, i32){
-        (self.direction.x, self.direction.y)
+    /// Puts the ball back at a point like `reset`, but served towards the left or the right side of the screen.
+    pub fn serve(&mut self, x:i32, y:i32, leftwards: bool, rng: &mut ChaCha20Rng){
+        self.reset(x, y, rng);
+        if (self.velocity.x < 0.0) != leftwards{
+            self.velocity.x = -self.velocity.x;
+        }
+    }
+
+    /// Returns the amount of pixels the ball moves each update.
+    pub fn get_velocity(&self) -> Vec2{
+        self.velocity
+    }
+
+    /// Returns how many pixels the ball travels each update.
+    pub fn get_speed(&self) -> f32{
+        self.velocity.length()
+    }
+
+    /// Sets how many pixels the ball travels each update without changing its direction. The speed
+    /// is limited to the ball's max speed.
+    pub fn set_speed(&mut self, speed: f32){
+        self.velocity = self.velocity.with_length(speed.min(self.max_speed));
+    }
+
+    /// Sets the speed the ball is served at from its next serve on.
+    pub fn set_serve_speed(&mut self, speed: f32){
+        self.serve_speed = speed.min(self.max_speed);
+    }
+
+    /// Sets the largest angle, in radians, a serve can point away from straight across the screen.
+    pub fn set_serve_cone(&mut self, serve_cone: f32){
+        self.serve_cone = serve_cone;
     }
 
     // Updates the now-current bounds of the circle.
     fn update_bounds(&mut self){
+        self.circle.primitive.center = self.position.to_point();
         self.bounds.top = self.circle.primitive.top_left().y;
         self.bounds.left = self.circle.primitive.top_left().x;
         self.bounds.bottom = self.circle.primitive.bottom_right().y;
         self.bounds.right = self.circle.primitive.bottom_right().x;
     }
 
-    /// Moves the ball to a Point
-    pub fn move_ball(&mut self, location:Point, disp: &mut Framebuffer){
-        self.background.draw(disp);
-        self.background.translate_mut(location);
-        self.circle.translate_mut(location);
-        self.circle.draw(disp);
+    /// Remembers where the ball is at the start of an update, so it can be drawn between updates.
+    pub fn start_step(&mut self){
+        self.previous = self.position;
+    }
+
+    /// Moves the ball by an offset
+    pub fn move_ball(&mut self, offset:Vec2){
+        self.position += offset;
         self.update_bounds();
     }
 
-    /// Moves the ball in the direction it is going towards
-    pub fn step_direction(&mut self, disp: &mut Framebuffer){
-        self.move_ball(self.direction, disp);
+    /// Draws the ball `alpha` of the way between where it was before its last move and where it is now.
+    pub fn draw<D: DrawTarget<Rgb888>>(&self, disp: &mut D, alpha: f32){
+        let center = self.previous.lerp(self.position, alpha).to_point();
+        let mut circle = self.circle;
+        circle.primitive.center = center;
+        let _ = circle.draw(disp);
     }
 
     /// Flips the direction the ball is going towards
+    #[allow(clippy::manual_is_multiple_of)] // is_multiple_of is newer than the compiler the PSP needs
     pub fn flip_direction(&mut self, axes: BallAxes){
         match axes{
             BallAxes::Horizontal => {
                 self.speed_counter += 1;
-                self.direction.x *= -1;
+                self.velocity.x *= -1.0;
                 if self.speed_counter % 3 == 0{ // Every third horizontal flip it will increase the speed of the ball by 1
-                    if self.direction.x > 0{ // makes the ball move faster on the x-axis
-                        self.direction.x += 1;
-                    } else{
-                        self.direction.x -= 1;
-                    }
+                    self.set_speed(self.get_speed() + 1.0);
                 }
             },
             BallAxes::Vertical => {
-                self.direction.y *= -1;
+                self.velocity.y *= -1.0;
             }
         }
     }
 
+    /// Bounces the ball back off a paddle. `offset` is where the ball hit the paddle, from -1 at its
+    /// top edge to 1 at its bottom edge, and `paddle_velocity` is how far the paddle moved down the
+    /// screen during the last update. Both steer the ball towards that side.
+    pub fn deflect(&mut self, offset: f32, paddle_velocity: f32){
+        self.flip_direction(BallAxes::Horizontal); // also speeds the ball up every third hit
+        let angle = (offset * MAX_BOUNCE_ANGLE + paddle_velocity * SPIN_PER_PIXEL).clamp(-MAX_BOUNCE_ANGLE, MAX_BOUNCE_ANGLE);
+        let velocity = Vec2::from_angle(angle, self.velocity.length());
+        if self.velocity.x < 0.0{ // keeps heading the way the flip sent it
+            self.velocity = Vec2::new(-velocity.x, velocity.y);
+        } else {
+            self.velocity = velocity;
+        }
+    }
+
+    /// Returns the radius of the ball
+    pub fn get_radius(&self) -> f32{
+        self.circle.primitive.radius as f32
+    }
+
+    /// Returns the center of the ball
+    pub fn get_position(&self) -> Vec2{
+        self.position
+    }
+
     /// Gets an immutable reference to the bounds of the ball
     pub fn get_bounds(&self) -> &BallBounds{
         &self.bounds
@@ -129,3 +212,60 @@ impl Ball{
 
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+
+    fn ball(speed: f32) -> Ball{
+        let mut rng = ChaCha20Rng::seed_from_u64(1);
+        Ball::new(240, 136, 10, 0.0, &mut rng).with_velocity(Vec2::new(speed, 0.0))
+    }
+
+    #[test]
+    fn every_third_paddle_hit_speeds_the_ball_up(){
+        let mut ball = ball(3.0);
+        let mut speeds = [0.0; 6];
+        for speed in speeds.iter_mut(){
+            ball.flip_direction(BallAxes::Horizontal);
+            *speed = ball.get_speed();
+        }
+        assert_eq!(speeds, [3.0, 3.0, 4.0, 4.0, 4.0, 5.0]);
+    }
+
+    #[test]
+    fn wall_bounces_do_not_speed_the_ball_up(){
+        let mut ball = ball(3.0);
+        for _ in 0..6{
+            ball.flip_direction(BallAxes::Vertical);
+        }
+        assert_eq!(ball.get_speed(), 3.0);
+        ball.flip_direction(BallAxes::Horizontal);
+        ball.flip_direction(BallAxes::Horizontal);
+        assert_eq!(ball.get_speed(), 3.0); // only two hits so far
+    }
+
+    #[test]
+    fn speeding_up_stops_at_the_max_speed(){
+        let mut ball = ball(3.0).with_max_speed(4.5);
+        for _ in 0..9{
+            ball.flip_direction(BallAxes::Horizontal);
+        }
+        assert_eq!(ball.get_speed(), 4.5);
+    }
+
+    #[test]
+    fn hitting_the_edge_of_a_paddle_bounces_at_the_steepest_angle(){
+        let mut top = ball(4.0);
+        top.deflect(-1.0, 0.0);
+        let velocity = top.get_velocity();
+        assert!(velocity.x < 0.0 && velocity.y < 0.0);
+        assert!((libm::atan2f(-velocity.y, -velocity.x) - MAX_BOUNCE_ANGLE).abs() < 1e-5);
+
+        let mut bottom = ball(4.0);
+        bottom.deflect(1.0, 0.0);
+        let velocity = bottom.get_velocity();
+        assert!(velocity.x < 0.0 && velocity.y > 0.0);
+        assert!((bottom.get_speed() - 4.0).abs() < 1e-5); // deflecting keeps the speed
+    }
+}
@@ -0,0 +1,98 @@
+use core::cell::Cell;
+#[cfg(feature = "psp")]
+use psp::sys::{sceRtcGetCurrentTick as getTick, sceRtcGetTickResolution as getTickResolution};
+
+const MICROS_PER_SECOND: u64 = 1_000_000;
+
+/// Something the current time can be read from.
+pub trait Clock {
+    /// Returns the current time in microseconds.
+    fn now(&self) -> u64;
+}
+
+/// Reads the time from the PSP's real time clock.
+#[cfg(feature = "psp")]
+pub struct PspClock {
+    ticks_per_second: u64,
+}
+
+#[cfg(feature = "psp")]
+impl PspClock {
+    pub fn new() -> Self {
+        let ticks_per_second = unsafe { getTickResolution() } as u64;
+        Self { ticks_per_second }
+    }
+}
+
+#[cfg(feature = "psp")]
+impl Clock for PspClock {
+    fn now(&self) -> u64 {
+        let mut tick = 0;
+        unsafe { getTick(&mut tick) };
+        // Split into whole seconds and the remainder so the conversion can't overflow
+        (tick / self.ticks_per_second) * MICROS_PER_SECOND + (tick % self.ticks_per_second) * MICROS_PER_SECOND / self.ticks_per_second
+    }
+}
+
+/// A clock that only moves when told to, for driving the game from tests.
+pub struct ManualClock {
+    now: Cell<u64>,
+}
+
+impl ManualClock {
+    pub fn new(start: u64) -> Self {
+        Self { now: Cell::new(start) }
+    }
+
+    /// Moves the clock forward by a number of microseconds.
+    pub fn advance(&self, micros: u64) {
+        self.now.set(self.now.get() + micros);
+    }
+
+    /// Sets the clock to a time in microseconds.
+    pub fn set(&self, micros: u64) {
+        self.now.set(micros);
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> u64 {
+        self.now.get()
+    }
+}
+
+/// Returns the amount of microseconds between updates when running at `per_second` updates a second.
+pub const fn interval(per_second: u32) -> u64 {
+    MICROS_PER_SECOND / per_second as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn manual_clock_only_moves_when_told() {
+        let clock = ManualClock::new(5);
+        assert_eq!(clock.now(), 5);
+        assert_eq!(clock.now(), 5);
+        clock.advance(1_000);
+        assert_eq!(clock.now(), 1_005);
+        clock.set(42);
+        assert_eq!(clock.now(), 42);
+    }
+
+    #[test]
+    fn intervals_are_in_microseconds() {
+        assert_eq!(interval(20), 50_000);
+        assert_eq!(interval(60), 16_666);
+
+        // A second on the clock holds as many whole intervals as updates were asked for
+        let clock = ManualClock::new(0);
+        let mut updates = 0;
+        while clock.now() + interval(20) <= MICROS_PER_SECOND {
+            clock.advance(interval(20));
+            updates += 1;
+        }
+        assert_eq!(updates, 20);
+    }
+}
@@ -0,0 +1,131 @@
+use crate::pong_controller::vector::Vec2;
+use crate::pong_controller::paddle::PaddleBounds;
+
+/// An axis aligned box.
+#[derive(Copy, Clone)]
+pub struct Aabb {
+    pub left: f32,
+    pub top: f32,
+    pub right: f32,
+    pub bottom: f32,
+}
+
+impl Aabb {
+    pub const fn new(left: f32, top: f32, right: f32, bottom: f32) -> Self {
+        Self { left, top, right, bottom }
+    }
+
+    /// Returns the box grown by `amount` on every side.
+    pub fn expand(self, amount: f32) -> Self {
+        Self::new(self.left - amount, self.top - amount, self.right + amount, self.bottom + amount)
+    }
+}
+
+impl From<&PaddleBounds> for Aabb {
+    fn from(bounds: &PaddleBounds) -> Self {
+        Self::new(bounds.left as f32, bounds.top as f32, bounds.right as f32, bounds.bottom as f32)
+    }
+}
+
+/// Where along a movement something was hit.
+#[derive(Copy, Clone)]
+pub struct Hit {
+    pub time: f32, // how far along the movement the hit happened, from 0 to 1
+    pub normal: Vec2, // points out of the side of the box that was hit
+}
+
+/// Sweeps a ball of `radius` from `start` by `motion` and returns where it first touches `rect`, if
+/// it does. The ball is treated as a square, like its bounds. A ball that starts inside the box is
+/// not counted as hitting it, so it can always leave.
+pub fn sweep(start: Vec2, motion: Vec2, radius: f32, rect: Aabb) -> Option<Hit> {
+    let rect = rect.expand(radius); // the ball's center hitting the grown box is the ball hitting the box
+    let (entry_x, exit_x, normal_x) = slab(start.x, motion.x, rect.left, rect.right)?;
+    let (entry_y, exit_y, normal_y) = slab(start.y, motion.y, rect.top, rect.bottom)?;
+
+    let (entry, normal) = if entry_x > entry_y {
+        (entry_x, Vec2::new(normal_x, 0.0))
+    } else {
+        (entry_y, Vec2::new(0.0, normal_y))
+    };
+    let exit = exit_x.min(exit_y);
+
+    if entry > exit || !(0.0..=1.0).contains(&entry) {
+        return None;
+    }
+    Some(Hit { time: entry, normal })
+}
+
+/// Returns when a point moving along one axis enters and leaves the span from `min` to `max`, and
+/// the direction of the side it enters through.
+fn slab(start: f32, motion: f32, min: f32, max: f32) -> Option<(f32, f32, f32)> {
+    if motion == 0.0 {
+        if start < min || start > max {
+            return None; // never gets inside
+        }
+        return Some((f32::NEG_INFINITY, f32::INFINITY, 0.0));
+    }
+    let to_min = (min - start) / motion;
+    let to_max = (max - start) / motion;
+    if motion > 0.0 {
+        Some((to_min, to_max, -1.0))
+    } else {
+        Some((to_max, to_min, 1.0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PADDLE: Aabb = Aabb::new(10.0, 136.0, 20.0, 176.0);
+    const RADIUS: f32 = 5.0;
+
+    fn assert_hit(hit: Option<Hit>, time: f32, normal: (f32, f32)) {
+        let hit = hit.expect("expected the ball to hit the box");
+        assert!((hit.time - time).abs() < 1e-6, "hit at {} instead of {}", hit.time, time);
+        assert_eq!((hit.normal.x, hit.normal.y), normal);
+    }
+
+    #[test]
+    fn a_fast_ball_cannot_pass_through_a_paddle() {
+        // Both ends of the movement are clear of the paddle, only the path between them crosses it
+        let hit = sweep(Vec2::new(100.0, 150.0), Vec2::new(-200.0, 0.0), RADIUS, PADDLE);
+        assert_hit(hit, 0.375, (1.0, 0.0));
+    }
+
+    #[test]
+    fn returns_the_time_and_side_of_the_first_contact() {
+        let from_above = sweep(Vec2::new(15.0, 100.0), Vec2::new(0.0, 40.0), RADIUS, PADDLE);
+        assert_hit(from_above, 0.775, (0.0, -1.0));
+
+        let from_below = sweep(Vec2::new(15.0, 200.0), Vec2::new(0.0, -38.0), RADIUS, PADDLE);
+        assert_hit(from_below, 0.5, (0.0, 1.0));
+
+        let from_the_left = sweep(Vec2::new(-5.0, 150.0), Vec2::new(20.0, 0.0), RADIUS, PADDLE);
+        assert_hit(from_the_left, 0.5, (-1.0, 0.0));
+
+        // Coming in at an angle, the side crossed last is the one that was hit
+        let diagonal = sweep(Vec2::new(40.0, 120.0), Vec2::new(-20.0, 20.0), RADIUS, PADDLE);
+        assert_hit(diagonal, 0.75, (1.0, 0.0));
+    }
+
+    #[test]
+    fn a_ball_starting_inside_a_box_can_leave_it() {
+        assert!(sweep(Vec2::new(15.0, 150.0), Vec2::new(-30.0, 0.0), RADIUS, PADDLE).is_none());
+        assert!(sweep(Vec2::new(22.0, 150.0), Vec2::new(10.0, 5.0), RADIUS, PADDLE).is_none()); // overlapping the edge
+    }
+
+    #[test]
+    fn moving_alongside_an_edge_misses() {
+        // Just above the paddle, moving straight across it
+        assert!(sweep(Vec2::new(100.0, 130.0), Vec2::new(-200.0, 0.0), RADIUS, PADDLE).is_none());
+        // Just to the right of it, moving straight down past it
+        assert!(sweep(Vec2::new(25.5, 100.0), Vec2::new(0.0, 100.0), RADIUS, PADDLE).is_none());
+    }
+
+    #[test]
+    fn stopping_short_is_not_a_hit() {
+        assert!(sweep(Vec2::new(100.0, 150.0), Vec2::new(-70.0, 0.0), RADIUS, PADDLE).is_none());
+        assert!(sweep(Vec2::new(100.0, 150.0), Vec2::new(70.0, 0.0), RADIUS, PADDLE).is_none()); // moving away
+    }
+}
@@ -0,0 +1,177 @@
+use embedded_graphics::DrawTarget;
+use embedded_graphics::drawable::Pixel;
+use embedded_graphics::geometry::Size;
+use embedded_graphics::pixelcolor::Rgb888;
+use embedded_graphics::prelude::Point;
+use core::convert::Infallible;
+use crate::pong_controller::{SCREEN_WIDTH, SCREEN_HEIGHT};
+#[cfg(feature = "psp")]
+use embedded_graphics::pixelcolor::RgbColor;
+#[cfg(feature = "psp")]
+use psp::sys::{self, DisplayMode, DisplayPixelFormat, DisplaySetBufSync};
+
+#[cfg(feature = "psp")]
+const BUF_WIDTH: usize = 512; // pixels in a row of the PSP's framebuffer, including the ones off the right of the screen
+#[cfg(feature = "psp")]
+const FRAME_SIZE: usize = BUF_WIDTH * SCREEN_HEIGHT as usize; // pixels in one framebuffer
+
+/// How many pixels there are on the screen.
+pub const PIXEL_COUNT: usize = (SCREEN_WIDTH * SCREEN_HEIGHT) as usize;
+
+/// A display that whole frames are drawn to out of sight before they are shown.
+pub trait Screen: DrawTarget<Rgb888> {
+    /// Shows the frame drawn since the last call, waiting for the display to finish showing the one before it.
+    fn present(&mut self);
+}
+
+/// A screen-sized draw target that throws away everything drawn to it. Used to run the game
+/// without any display, e.g. in tests.
+pub struct NullDisplay;
+
+impl DrawTarget<Rgb888> for NullDisplay {
+    type Error = Infallible;
+
+    fn draw_pixel(&mut self, _pixel: Pixel<Rgb888>) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn clear(&mut self, _color: Rgb888) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn size(&self) -> Size {
+        Size::new(SCREEN_WIDTH, SCREEN_HEIGHT)
+    }
+}
+
+impl Screen for NullDisplay {
+    fn present(&mut self) {}
+}
+
+/// A screen-sized image kept in memory that it is handed. Nothing is ever shown, but everything drawn
+/// can be read back, e.g. to compare frames against reference images in tests.
+pub struct MemoryDisplay<'a> {
+    pixels: &'a mut [Rgb888], // row by row from the top left
+}
+
+impl<'a> MemoryDisplay<'a> {
+    /// Draws into `pixels`, which has to hold exactly `PIXEL_COUNT` pixels.
+    pub fn new(pixels: &'a mut [Rgb888]) -> Self {
+        assert_eq!(pixels.len(), PIXEL_COUNT, "A MemoryDisplay needs one pixel for every point on the screen.");
+        Self { pixels }
+    }
+
+    /// Returns the colour of the pixel at a point, or `None` if the point is off the screen.
+    pub fn pixel(&self, point: Point) -> Option<Rgb888> {
+        Self::index(point).map(|i| self.pixels[i])
+    }
+
+    /// Returns every pixel, row by row from the top left.
+    pub fn pixels(&self) -> &[Rgb888] {
+        self.pixels
+    }
+
+    fn index(point: Point) -> Option<usize> {
+        if point.x >= 0 && point.y >= 0 && (point.x as u32) < SCREEN_WIDTH && (point.y as u32) < SCREEN_HEIGHT {
+            Some(point.x as usize + point.y as usize * SCREEN_WIDTH as usize)
+        } else {
+            None
+        }
+    }
+}
+
+impl DrawTarget<Rgb888> for MemoryDisplay<'_> {
+    type Error = Infallible;
+
+    fn draw_pixel(&mut self, pixel: Pixel<Rgb888>) -> Result<(), Self::Error> {
+        let Pixel(point, color) = pixel;
+        if let Some(i) = Self::index(point) {
+            self.pixels[i] = color;
+        }
+        Ok(())
+    }
+
+    fn clear(&mut self, color: Rgb888) -> Result<(), Self::Error> {
+        for pixel in self.pixels.iter_mut() {
+            *pixel = color;
+        }
+        Ok(())
+    }
+
+    fn size(&self) -> Size {
+        Size::new(SCREEN_WIDTH, SCREEN_HEIGHT)
+    }
+}
+
+impl Screen for MemoryDisplay<'_> {
+    fn present(&mut self) {}
+}
+
+/// Two framebuffers in the PSP's video memory. One is shown while the other is drawn to, and they swap
+/// places during the vertical blank, so a frame is never seen half drawn.
+#[cfg(feature = "psp")]
+pub struct PspScreen {
+    vram_base: *mut u32,
+    back: usize, // which of the two framebuffers is being drawn to
+}
+
+#[cfg(feature = "psp")]
+impl PspScreen {
+    pub fn new() -> Self {
+        unsafe {
+            sys::sceDisplaySetMode(DisplayMode::Lcd, SCREEN_WIDTH as usize, SCREEN_HEIGHT as usize);
+            let vram_base = (0x4000_0000u32 | sys::sceGeEdramGetAddr() as u32) as *mut u32; // uncached
+            sys::sceDisplaySetFrameBuf(vram_base as *const u8, BUF_WIDTH, DisplayPixelFormat::Psm8888, DisplaySetBufSync::NextFrame);
+            Self { vram_base, back: 1 }
+        }
+    }
+
+    /// Returns the start of the framebuffer being drawn to.
+    fn back_buffer(&self) -> *mut u32 {
+        unsafe { self.vram_base.add(self.back * FRAME_SIZE) }
+    }
+
+    /// Packs a colour the way the PSP's 8888 pixel format stores it.
+    fn pack(color: Rgb888) -> u32 {
+        (color.r() as u32) | ((color.g() as u32) << 8) | ((color.b() as u32) << 16)
+    }
+}
+
+#[cfg(feature = "psp")]
+impl DrawTarget<Rgb888> for PspScreen {
+    type Error = Infallible;
+
+    fn draw_pixel(&mut self, pixel: Pixel<Rgb888>) -> Result<(), Self::Error> {
+        let Pixel(point, color) = pixel;
+        if point.x >= 0 && point.y >= 0 && (point.x as u32) < SCREEN_WIDTH && (point.y as u32) < SCREEN_HEIGHT {
+            unsafe {
+                *self.back_buffer().add(point.x as usize + point.y as usize * BUF_WIDTH) = Self::pack(color);
+            }
+        }
+        Ok(())
+    }
+
+    fn clear(&mut self, color: Rgb888) -> Result<(), Self::Error> {
+        let packed = Self::pack(color);
+        let buffer = self.back_buffer();
+        for i in 0..FRAME_SIZE {
+            unsafe { *buffer.add(i) = packed; }
+        }
+        Ok(())
+    }
+
+    fn size(&self) -> Size {
+        Size::new(SCREEN_WIDTH, SCREEN_HEIGHT)
+    }
+}
+
+#[cfg(feature = "psp")]
+impl Screen for PspScreen {
+    fn present(&mut self) {
+        unsafe {
+            sys::sceDisplayWaitVblankStart();
+            sys::sceDisplaySetFrameBuf(self.back_buffer() as *const u8, BUF_WIDTH, DisplayPixelFormat::Psm8888, DisplaySetBufSync::NextFrame);
+        }
+        self.back ^= 1;
+    }
+}
@@ -0,0 +1,169 @@
+use bitflags::bitflags;
+#[cfg(feature = "psp")]
+use psp::sys::{SceCtrlData, sceCtrlReadBufferPositive as getInput};
+
+bitflags! {
+    /// Buttons the game reacts to. The bits match the PSP's `CtrlButtons`, so the game logic does
+    /// not have to depend on the `psp` crate.
+    #[derive(Default)]
+    pub struct Buttons: u32 {
+        const SELECT = 0x000001;
+        const START = 0x000008;
+        const UP = 0x000010;
+        const RIGHT = 0x000020;
+        const DOWN = 0x000040;
+        const LEFT = 0x000080;
+        const LTRIGGER = 0x000100;
+        const RTRIGGER = 0x000200;
+        const TRIANGLE = 0x001000;
+        const CIRCLE = 0x002000;
+        const CROSS = 0x004000;
+        const SQUARE = 0x008000;
+    }
+}
+
+#[cfg(feature = "psp")]
+impl From<psp::sys::CtrlButtons> for Buttons {
+    fn from(buttons: psp::sys::CtrlButtons) -> Self {
+        Buttons::from_bits_truncate(buttons.bits())
+    }
+}
+
+/// Something the buttons being held down can be read from.
+pub trait InputSource {
+    /// Returns the buttons that are currently held down.
+    fn read(&mut self) -> Buttons;
+}
+
+/// Reads the buttons from the PSP's controller.
+#[cfg(feature = "psp")]
+pub struct PspInput {
+    data: SceCtrlData,
+}
+
+#[cfg(feature = "psp")]
+impl PspInput {
+    pub fn new() -> Self {
+        Self { data: SceCtrlData::default() }
+    }
+}
+
+#[cfg(feature = "psp")]
+impl InputSource for PspInput {
+    fn read(&mut self) -> Buttons {
+        unsafe { getInput(&mut self.data, 1) }; // updates the buttons currently being pressed
+        Buttons::from(self.data.buttons)
+    }
+}
+
+/// Replays a fixed list of button states, one per read. Once the script has run out no buttons are held.
+pub struct ScriptedInput<'a> {
+    frames: &'a [Buttons],
+    position: usize,
+}
+
+impl<'a> ScriptedInput<'a> {
+    pub fn new(frames: &'a [Buttons]) -> Self {
+        Self { frames, position: 0 }
+    }
+
+    /// Checks to see if every frame of the script has been read.
+    pub fn is_finished(&self) -> bool {
+        self.position >= self.frames.len()
+    }
+}
+
+impl InputSource for ScriptedInput<'_> {
+    fn read(&mut self) -> Buttons {
+        let buttons = self.frames.get(self.position).copied().unwrap_or_else(Buttons::empty);
+        self.position = self.position.saturating_add(1);
+        buttons
+    }
+}
+
+/// Tracks which buttons are held, and which were pressed or released since the last update.
+#[derive(Copy, Clone, Default)]
+pub struct InputState {
+    held: Buttons,
+    pressed: Buttons,
+    released: Buttons,
+}
+
+impl InputState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Updates the state with the buttons currently held down.
+    pub fn update(&mut self, buttons: Buttons) {
+        self.pressed = buttons & !self.held;
+        self.released = self.held & !buttons;
+        self.held = buttons;
+    }
+
+    /// Checks to see if all of the given buttons are held down.
+    pub fn held(&self, buttons: Buttons) -> bool {
+        self.held.contains(buttons)
+    }
+
+    /// Checks to see if all of the given buttons went down during the last update.
+    pub fn pressed(&self, buttons: Buttons) -> bool {
+        self.pressed.contains(buttons)
+    }
+
+    /// Checks to see if all of the given buttons went up during the last update.
+    pub fn released(&self, buttons: Buttons) -> bool {
+        self.released.contains(buttons)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Feeds a script through an `InputState` and returns the state after every read.
+    fn replay(frames: &[Buttons]) -> [InputState; 5] {
+        let mut input = ScriptedInput::new(frames);
+        let mut state = InputState::new();
+        let mut states = [InputState::new(); 5];
+        for slot in states.iter_mut() {
+            state.update(input.read());
+            *slot = state;
+        }
+        states
+    }
+
+    #[test]
+    fn pressing_fires_once_while_held() {
+        let states = replay(&[Buttons::empty(), Buttons::CROSS, Buttons::CROSS, Buttons::CROSS, Buttons::empty()]);
+        let pressed = states.iter().map(|state| state.pressed(Buttons::CROSS));
+        let held = states.iter().map(|state| state.held(Buttons::CROSS));
+        assert!(pressed.eq([false, true, false, false, false].iter().copied()));
+        assert!(held.eq([false, true, true, true, false].iter().copied()));
+    }
+
+    #[test]
+    fn releasing_fires_once_after_letting_go() {
+        let states = replay(&[Buttons::UP, Buttons::UP, Buttons::empty(), Buttons::empty(), Buttons::UP]);
+        let released = states.iter().map(|state| state.released(Buttons::UP));
+        assert!(released.eq([false, false, true, false, false].iter().copied()));
+    }
+
+    #[test]
+    fn buttons_have_edges_of_their_own() {
+        let states = replay(&[Buttons::UP, Buttons::UP | Buttons::CROSS, Buttons::CROSS, Buttons::empty(), Buttons::empty()]);
+        assert!(states[1].pressed(Buttons::CROSS) && !states[1].pressed(Buttons::UP));
+        assert!(states[2].released(Buttons::UP) && !states[2].released(Buttons::CROSS));
+        assert!(states[3].released(Buttons::CROSS));
+    }
+
+    #[test]
+    fn a_finished_script_holds_nothing() {
+        let frames = [Buttons::START];
+        let mut input = ScriptedInput::new(&frames);
+        assert!(!input.is_finished());
+        assert_eq!(input.read(), Buttons::START);
+        assert!(input.is_finished());
+        assert_eq!(input.read(), Buttons::empty());
+    }
+}
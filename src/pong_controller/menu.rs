@@ -1,133 +1,678 @@
 
-use psp::embedded_graphics::Framebuffer;
+use embedded_graphics::DrawTarget;
 use embedded_graphics::style::{PrimitiveStyleBuilder, TextStyleBuilder, Styled, TextStyle, PrimitiveStyle};
 use embedded_graphics::pixelcolor::Rgb888;
-use embedded_graphics::prelude::{RgbColor, Point, Primitive, Dimensions, Transform, Drawable};
-use embedded_graphics::primitives::Rectangle;
-use psp::{SCREEN_WIDTH, SCREEN_HEIGHT};
-use embedded_graphics::fonts::{Font12x16, Text, Font};
-use crate::pong_controller::opponent::OpponentDifficulty;
-
-#[feature(min_const_generics)]
-pub struct Menu<'a, T, U, const N:usize>
-where T: Font + Copy + Clone,
+use embedded_graphics::prelude::{RgbColor, Point, Size, Primitive, Dimensions, Drawable};
+use embedded_graphics::primitives::{Rectangle, Triangle};
+use embedded_graphics::fonts::{Text, Font};
+use crate::pong_controller::{SCREEN_WIDTH, SCREEN_HEIGHT};
+use arrayvec::ArrayString;
+use core::fmt::Write;
+
+const TEXT_MARGIN: i32 = 5; // space between the edge of the menu and its text
+const INDICATOR_SIZE: i32 = 6; // width and height of the arrows showing there are more items to scroll to
+const SLIDER_WIDTH: i32 = 60; // length of a slider's bar in pixels
+
+/// What an item in a menu is, along with the value it holds.
+#[derive(Copy, Clone)]
+pub enum MenuItemKind<'a, A: Copy> {
+    Button, // only does something when it is selected with cross
+    Toggle(bool), // on or off
+    Picker(&'a [(&'a str, A)], usize), // one of a list of named choices, each standing for an action, along with the one that is picked
+    Slider{value: i32, min: i32, max: i32, step: i32}, // a number between min and max, moved by step at a time
+}
+
+impl<'a, A: Copy> MenuItemKind<'a, A> {
+    /// Returns the value the item holds: 1 or 0 for a toggle, the index of the picked choice for a
+    /// picker and the number for a slider. Buttons are always 0.
+    pub fn value(&self) -> i32{
+        match *self{
+            MenuItemKind::Button => 0,
+            MenuItemKind::Toggle(on) => on as i32,
+            MenuItemKind::Picker(_, index) => index as i32,
+            MenuItemKind::Slider{value, ..} => value,
+        }
+    }
+
+    /// Sets the value the item holds, kept within what the item can hold.
+    fn set_value(&mut self, new_value: i32){
+        match self{
+            MenuItemKind::Button => {},
+            MenuItemKind::Toggle(on) => *on = new_value != 0,
+            MenuItemKind::Picker(choices, index) => *index = new_value.clamp(0, choices.len() as i32 - 1) as usize,
+            MenuItemKind::Slider{value, min, max, ..} => *value = new_value.clamp(*min, *max),
+        }
+    }
+
+    /// Moves the value one step up or down. Toggles flip either way and pickers wrap around.
+    /// Returns whether the value changed.
+    fn change(&mut self, up: bool) -> bool{
+        match self{
+            MenuItemKind::Button => false,
+            MenuItemKind::Toggle(on) => {
+                *on = !*on;
+                true
+            },
+            MenuItemKind::Picker(choices, index) => {
+                let count = choices.len();
+                let picked = if up { (*index + 1) % count } else { (*index + count - 1) % count };
+                let changed = picked != *index;
+                *index = picked;
+                changed
+            },
+            MenuItemKind::Slider{value, min, max, step} => {
+                let moved = if up { (*value + *step).min(*max) } else { (*value - *step).max(*min) };
+                let changed = moved != *value;
+                *value = moved;
+                changed
+            },
+        }
+    }
+}
+
+/// An item of a menu: the text shown for it, the action it stands for and what kind of item it is.
+#[derive(Copy, Clone)]
+pub struct MenuItem<'a, A: Copy> {
+    label: &'a str,
+    action: A,
+    kind: MenuItemKind<'a, A>,
+}
+
+impl<'a, A: Copy> MenuItem<'a, A> {
+    /// An item that only does something when it is selected.
+    pub fn button(label: &'a str, action: A) -> Self{
+        Self {label, action, kind: MenuItemKind::Button}
+    }
+
+    /// An item that is either on or off.
+    pub fn toggle(label: &'a str, action: A, on: bool) -> Self{
+        Self {label, action, kind: MenuItemKind::Toggle(on)}
+    }
+
+    /// An item that is one of `choices`, starting at the one at `index`. The item stands for the
+    /// action of whichever choice is picked.
+    pub fn picker(label: &'a str, choices: &'a [(&'a str, A)], index: usize) -> Self{
+        assert!(!choices.is_empty(), "A picker needs at least one choice.");
+        let index = index.min(choices.len()-1);
+        Self {label, action: choices[index].1, kind: MenuItemKind::Picker(choices, index)}
+    }
+
+    /// An item holding a number from `min` to `max`, changed by `step` at a time.
+    pub fn slider(label: &'a str, action: A, min: i32, max: i32, step: i32, value: i32) -> Self{
+        Self {label, action, kind: MenuItemKind::Slider{value: value.clamp(min, max), min, max, step}}
+    }
+}
+
+impl<'a, A: Copy> From<(&'a str, A)> for MenuItem<'a, A> {
+    fn from((label, action): (&'a str, A)) -> Self{
+        Self::button(label, action)
+    }
+}
+
+/// A list of items to pick from, each of which stands for an action of type `A`.
+pub struct Menu<'a, A, T, U, const N:usize>
+where A: Copy,
+    T: Font + Copy + Clone,
     U: Font + Copy + Clone,
 {
     menu_items: [Styled<Text<'a>, TextStyle<Rgb888, T>>; N],
+    actions: [A; N],
+    kinds: [MenuItemKind<'a, A>; N],
     menu_title: Styled<Text<'a>, TextStyle<Rgb888, U>>,
     selected_counter:usize,
-    menu_background: Styled<Rectangle, PrimitiveStyle<Rgb888>>,
     menu_foreground: Styled<Rectangle, PrimitiveStyle<Rgb888>>,
     selected_style: TextStyle<Rgb888, T>,
-    unselected_style: TextStyle<Rgb888, T>
+    unselected_style: TextStyle<Rgb888, T>,
+    wrapping: bool, // moving past the last item selects the first one and the other way around
+    list_area: Rectangle, // the part of the menu the items are shown in
+    first_item: Point, // where the first visible item is drawn
+    item_spacing: i32,
+    visible_items: usize, // how many items fit in the list area at once
+    scroll_offset: usize, // index of the first visible item
+    scroll_up_indicator: Styled<Triangle, PrimitiveStyle<Rgb888>>,
+    scroll_down_indicator: Styled<Triangle, PrimitiveStyle<Rgb888>>,
 }
 
-impl<'a, T,U, const N:usize> Menu<'a,T,U, N>
-where T: Font + Copy + Clone,
+impl<'a, A, T,U, const N:usize> Menu<'a,A,T,U, N>
+where A: Copy,
+      T: Font + Copy + Clone,
       U: Font + Copy + Clone,
 {
-    pub fn new(menu_title:&'a str, menu_items_str:[&'a str; N], item_font:T, title_font:U, item_spacing:usize) -> Self{
-        let background = PrimitiveStyleBuilder::new()
-            .fill_color(Rgb888::BLACK)
-            .build();
-        let foreground = PrimitiveStyleBuilder::new()
-            .fill_color(Rgb888::CYAN)
-            .build();
-
-        let menu_background = Rectangle::new(Point::new(SCREEN_WIDTH as i32/2 - 100, 20),
-                                                   Point::new(SCREEN_WIDTH as i32/2 + 100, SCREEN_HEIGHT as i32 - 20))
-            .into_styled(background);
+    pub fn new<I: Into<MenuItem<'a, A>> + Copy>(menu_title:&'a str, menu_items_str:[I; N], item_font:T, title_font:U, item_spacing:usize) -> Self{
+        MenuBuilder::new()
+            .set_title(menu_title)
+            .set_items(menu_items_str)
+            .set_item_font(item_font)
+            .set_title_font(title_font)
+            .set_item_spacing(item_spacing)
+            .build()
+    }
 
-        let menu_foreground = Rectangle::new(Point::new(SCREEN_WIDTH as i32/2 - 100, 20),
-                                             Point::new(SCREEN_WIDTH as i32/2 + 100, SCREEN_HEIGHT as i32 - 20))
-            .into_styled(foreground);
-        let unselected_style = TextStyleBuilder::new(item_font)
-            .text_color(Rgb888::WHITE)
-            .background_color(Rgb888::CYAN)
-            .build();
-        let selected_style = TextStyleBuilder::new(item_font)
-            .text_color(Rgb888::YELLOW)
-            .background_color(Rgb888::CYAN)
-            .build();
-        let title_style = TextStyleBuilder::new(title_font)
-            .background_color(Rgb888::CYAN)
-            .text_color(Rgb888::RED)
-            .build();
-        let menu_title = Text::new(menu_title, Point::new(menu_foreground.top_left().x+5, menu_foreground.top_left().y))
-            .into_styled(title_style);
+    /// Draws the menu with the items that are scrolled into view, and arrows if there are more above or below them
+    pub fn draw<D: DrawTarget<Rgb888>>(&self, disp: &mut D){
+        let _ = self.menu_foreground.draw(disp);
+        let _ = self.menu_title.draw(disp);
+        for index in (0..N).skip(self.scroll_offset).take(self.visible_items){
+            self.draw_item(index, disp);
+        }
+        if self.scroll_offset > 0{
+            let _ = self.scroll_up_indicator.draw(disp);
+        }
+        if self.scroll_offset + self.visible_items < N{
+            let _ = self.scroll_down_indicator.draw(disp);
+        }
+    }
 
-        let mut menu_items = [Text::new("", Point::new(0, 0)).into_styled(unselected_style); N];
-        let x_coord = menu_foreground.top_left().x+5;
-        let mut y_coord = menu_foreground.top_left().y+item_spacing as i32;
-        for i in 0..N{
-            menu_items[i] = Text::new(menu_items_str[i], Point::new(x_coord, y_coord))
-                .into_styled(unselected_style);
-            y_coord += item_spacing as i32;
+    /// Draws the item at `index` with its value lined up against the right side of the list
+    fn draw_item<D: DrawTarget<Rgb888>>(&self, index: usize, disp: &mut D){
+        let item = &self.menu_items[index];
+        let _ = item.draw(disp);
+        if let MenuItemKind::Button = self.kinds[index]{
+            return;
         }
-        menu_items[0].style = selected_style;
 
-        Self { menu_items, menu_title, selected_counter: 0, menu_background,menu_foreground, unselected_style, selected_style}
+        let char_size = T::CHARACTER_SIZE;
+        let position = item.primitive.position;
+        let value_right = self.list_area.bottom_right.x - 2*TEXT_MARGIN - INDICATOR_SIZE;
+        let mut value_buf = ArrayString::<[_; 24]>::new();
+        let text_right = match self.kinds[index]{
+            MenuItemKind::Button => return,
+            MenuItemKind::Toggle(on) => {
+                let _ = value_buf.try_push_str(if on { "On" } else { "Off" });
+                value_right
+            },
+            MenuItemKind::Picker(choices, picked) => {
+                let _ = write!(&mut value_buf, "<{}>", choices[picked].0);
+                value_right
+            },
+            MenuItemKind::Slider{value, min, max, ..} => {
+                let color = item.style.text_color.unwrap_or(Rgb888::WHITE);
+                let bar_top = position.y + char_size.height as i32/4;
+                let bar_bottom = position.y + char_size.height as i32*3/4;
+                let bar_left = value_right - SLIDER_WIDTH;
+                let filled = if max > min { (value - min) * SLIDER_WIDTH / (max - min) } else { SLIDER_WIDTH };
+                let _ = Rectangle::new(Point::new(bar_left, bar_top), Point::new(value_right, bar_bottom))
+                    .into_styled(PrimitiveStyleBuilder::new().stroke_color(color).stroke_width(1).build())
+                    .draw(disp);
+                let _ = Rectangle::new(Point::new(bar_left, bar_top), Point::new(bar_left + filled, bar_bottom))
+                    .into_styled(PrimitiveStyle::with_fill(color))
+                    .draw(disp);
+                let _ = write!(&mut value_buf, "{}", value);
+                bar_left - TEXT_MARGIN
+            },
+        };
+        let text_left = text_right - value_buf.len() as i32 * char_size.width as i32;
+        let _ = Text::new(&value_buf, Point::new(text_left, position.y))
+            .into_styled(item.style)
+            .draw(disp);
     }
 
-    /// Displays the menu on the screen
-    pub fn show_menu(&mut self, disp: &mut Framebuffer){
-        self.menu_foreground.draw(disp);
-        self.menu_title.draw(disp);
-        for item in &mut self.menu_items{
-            item.draw(disp);
+    /// Moves every item to where it is drawn with the current scroll offset
+    fn layout_items(&mut self){
+        for (i, item) in self.menu_items.iter_mut().enumerate(){
+            let row = i as i32 - self.scroll_offset as i32;
+            item.primitive.position = Point::new(self.first_item.x, self.first_item.y + row*self.item_spacing);
         }
     }
 
+    /// Highlights the menu item at `index` instead of the currently selected one, scrolling it into view
+    fn select(&mut self, index: usize){
+        self.menu_items[self.selected_counter].style = self.unselected_style;
+        self.menu_items[index].style = self.selected_style;
+
+        let scroll_offset = if index < self.scroll_offset{
+            index
+        } else if index >= self.scroll_offset + self.visible_items{
+            index + 1 - self.visible_items
+        } else {
+            self.scroll_offset
+        };
+
+        if scroll_offset != self.scroll_offset{
+            self.scroll_offset = scroll_offset;
+            self.layout_items();
+        }
+        self.selected_counter = index;
+    }
+
     /// Moves the currently highlighted menu item to one lower
-    pub fn move_down(&mut self, disp: &mut Framebuffer){
+    pub fn move_down(&mut self){
         if self.selected_counter+1 < N{
-            self.menu_items[self.selected_counter].style = self.unselected_style;
-            self.menu_items[self.selected_counter+1].style = self.selected_style;
-            self.menu_items[self.selected_counter].draw(disp);
-            self.menu_items[self.selected_counter+1].draw(disp);
-            self.selected_counter += 1;
+            self.select(self.selected_counter+1);
+        } else if self.wrapping{
+            self.select(0);
         }
     }
     /// Moves the currently highlighted menu item to one higher
-    pub fn move_up(&mut self, disp: &mut Framebuffer){
+    pub fn move_up(&mut self){
         if self.selected_counter != 0{
-            self.menu_items[self.selected_counter].style = self.unselected_style;
-            self.menu_items[self.selected_counter-1].style = self.selected_style;
-            self.menu_items[self.selected_counter].draw(disp);
-            self.menu_items[self.selected_counter-1].draw(disp);
-            self.selected_counter -= 1;
+            self.select(self.selected_counter-1);
+        } else if self.wrapping{
+            self.select(N-1);
+        }
+    }
+
+    /// Changes the value of the selected item one step down. Returns whether the value changed.
+    pub fn move_left(&mut self) -> bool{
+        self.kinds[self.selected_counter].change(false)
+    }
+
+    /// Changes the value of the selected item one step up. Returns whether the value changed.
+    pub fn move_right(&mut self) -> bool{
+        self.kinds[self.selected_counter].change(true)
+    }
+
+    /// Flips the selected item if it is a toggle. Returns whether it was flipped.
+    pub fn press(&mut self) -> bool{
+        match self.kinds[self.selected_counter]{
+            MenuItemKind::Toggle(_) => self.kinds[self.selected_counter].change(true),
+            _ => false,
+        }
+    }
+
+    /// Returns the action of the currently selected menu item, which for a picker is the action of the picked choice
+    pub fn selected_action(&self) -> A{
+        match self.kinds[self.selected_counter]{
+            MenuItemKind::Picker(choices, picked) => choices[picked].1,
+            _ => self.actions[self.selected_counter],
         }
     }
 
-    /// Returns the currently selected menu item's index
-    pub fn return_selected_index(&self) -> usize{
-        self.selected_counter
+    /// Returns the value held by the currently selected menu item
+    pub fn selected_value(&self) -> i32{
+        self.kinds[self.selected_counter].value()
     }
 
-    /// Turns the menu to black, effectively hiding it from view. Also resets the selected counter to 0.
-    pub fn hide_menu(&mut self, disp: &mut Framebuffer){
-        self.menu_background.draw(disp);
+    /// Selects the first item again and scrolls back to the top, ready for the next time the menu is opened.
+    pub fn reset(&mut self){
         self.menu_items[self.selected_counter].style = self.unselected_style;
         self.menu_items[0].style = self.selected_style;
         self.selected_counter = 0;
+        self.scroll_offset = 0;
+        self.layout_items();
     }
 }
 
-#[feature(min_const_generics)]
-pub struct MenuBuilder<'a, T, U, const N: usize>
-where   T: Font + Copy + Clone,
+impl<'a, A, T,U, const N:usize> Menu<'a,A,T,U, N>
+where A: Copy + PartialEq,
+      T: Font + Copy + Clone,
+      U: Font + Copy + Clone,
+{
+    /// Sets the value held by every item standing for `action`.
+    pub fn set_value(&mut self, action: A, value: i32){
+        for (kind, _) in self.kinds.iter_mut().zip(self.actions.iter()).filter(|(_, &a)| a == action){
+            kind.set_value(value);
+        }
+    }
+
+    /// Picks the choice standing for `action` in every picker that has one. Pickers without it are left as they are.
+    pub fn pick(&mut self, action: A){
+        for kind in self.kinds.iter_mut(){
+            if let MenuItemKind::Picker(choices, picked) = kind{
+                if let Some(index) = choices.iter().position(|&(_, a)| a == action){
+                    *picked = index;
+                }
+            }
+        }
+    }
+
+    /// Highlights the first item standing for `action`, if there is one.
+    pub fn select_action(&mut self, action: A){
+        if let Some(index) = self.actions.iter().position(|&a| a == action){
+            self.select(index);
+        }
+    }
+}
+
+/// Builds a `Menu`. The title, items and both fonts have to be given, everything else falls back
+/// to a cyan box in the middle of the screen.
+pub struct MenuBuilder<'a, A, T, U, const N: usize>
+where   A: Copy,
+        T: Font + Copy + Clone,
         U: Font + Copy + Clone,
 {
-    items: Option<[&'a str; N]>,
+    items: Option<[MenuItem<'a, A>; N]>,
     title: Option<&'a str>,
-    menu_background_color: Option<Rgb888>,
+    menu_background_color: Option<Rgb888>, // behind the text of the title and items
     menu_foreground_color: Option<Rgb888>,
     menu_stroke_color: Option<Rgb888>,
-    menu_stroke_width: Option<usize>,
-    menu_title_font: Option<T>,
-    menu_item_font: Option<U>,
+    menu_stroke_width: Option<u32>,
+    menu_item_font: Option<T>,
+    menu_title_font: Option<U>,
     menu_title_color: Option<Rgb888>,
     menu_item_color: Option<Rgb888>,
+    menu_selected_item_color: Option<Rgb888>,
+    menu_position: Option<Point>,
+    menu_size: Option<Size>,
+    menu_item_spacing: Option<usize>,
     menu_wrapping_enabled: Option<bool>,
 }
 
+impl<'a, A, T, U, const N: usize> MenuBuilder<'a, A, T, U, N>
+where   A: Copy,
+        T: Font + Copy + Clone,
+        U: Font + Copy + Clone,
+{
+    pub fn new() -> Self{
+        Self{ items: None, title: None, menu_background_color: None, menu_foreground_color: None,
+            menu_stroke_color: None, menu_stroke_width: None, menu_item_font: None, menu_title_font: None,
+            menu_title_color: None, menu_item_color: None, menu_selected_item_color: None, menu_position: None,
+            menu_size: None, menu_item_spacing: None, menu_wrapping_enabled: None}
+    }
+
+    /// Sets the items of the menu. Plain `(text, action)` pairs become buttons.
+    pub fn set_items<I: Into<MenuItem<'a, A>> + Copy>(mut self, items: [I; N]) -> Self{
+        if let Some(&first) = items.first(){ // a menu without items is turned down by `build`
+            let mut converted = [first.into(); N];
+            for (item, &from) in converted.iter_mut().zip(items.iter()){
+                *item = from.into();
+            }
+            self.items = Some(converted);
+        }
+        self
+    }
+
+    pub fn set_title(mut self, title: &'a str) -> Self{
+        self.title = Some(title);
+        self
+    }
+
+    /// Sets the colour drawn behind the text of the title and items. Falls back to the colour of the menu's box.
+    pub fn set_background_color(mut self, color: Rgb888) -> Self{
+        self.menu_background_color = Some(color);
+        self
+    }
+
+    /// Sets the colour of the menu's box
+    pub fn set_foreground_color(mut self, color: Rgb888) -> Self{
+        self.menu_foreground_color = Some(color);
+        self
+    }
+
+    /// Sets the colour of the border around the menu's box
+    pub fn set_stroke_color(mut self, color: Rgb888) -> Self{
+        self.menu_stroke_color = Some(color);
+        self
+    }
+
+    /// Sets the width of the border around the menu's box
+    pub fn set_stroke_width(mut self, width: u32) -> Self{
+        self.menu_stroke_width = Some(width);
+        self
+    }
+
+    pub fn set_item_font(mut self, font: T) -> Self{
+        self.menu_item_font = Some(font);
+        self
+    }
+
+    pub fn set_title_font(mut self, font: U) -> Self{
+        self.menu_title_font = Some(font);
+        self
+    }
+
+    pub fn set_title_color(mut self, color: Rgb888) -> Self{
+        self.menu_title_color = Some(color);
+        self
+    }
+
+    pub fn set_item_color(mut self, color: Rgb888) -> Self{
+        self.menu_item_color = Some(color);
+        self
+    }
+
+    /// Sets the colour of the item that is currently selected
+    pub fn set_selected_item_color(mut self, color: Rgb888) -> Self{
+        self.menu_selected_item_color = Some(color);
+        self
+    }
+
+    /// Sets where the top left corner of the menu's box is
+    pub fn set_position(mut self, position: Point) -> Self{
+        self.menu_position = Some(position);
+        self
+    }
+
+    pub fn set_size(mut self, size: Size) -> Self{
+        self.menu_size = Some(size);
+        self
+    }
+
+    /// Sets how far apart the items are vertically, in pixels
+    pub fn set_item_spacing(mut self, spacing: usize) -> Self{
+        self.menu_item_spacing = Some(spacing);
+        self
+    }
+
+    /// Sets whether moving past either end of the menu wraps around to the other end
+    pub fn set_wrapping(mut self, wrapping: bool) -> Self{
+        self.menu_wrapping_enabled = Some(wrapping);
+        self
+    }
+
+    /// Creates the menu. Panics if anything it needs is missing or if it has no items.
+    pub fn build(self) -> Menu<'a, A, T, U, N>{
+        assert!(N > 0, "A Menu needs at least one item.");
+        let (items, title, item_font, title_font) = match (self.items, self.title, self.menu_item_font, self.menu_title_font){
+            (Some(items), Some(title), Some(item_font), Some(title_font)) => (items, title, item_font, title_font),
+            _ => panic!("The MenuBuilder was not given enough information to create a Menu."),
+        };
+        let foreground_color = self.menu_foreground_color.unwrap_or(Rgb888::CYAN);
+        let background_color = self.menu_background_color.unwrap_or(foreground_color);
+        let stroke_color = self.menu_stroke_color.unwrap_or(foreground_color);
+        let stroke_width = self.menu_stroke_width.unwrap_or(0);
+        let item_spacing = self.menu_item_spacing.unwrap_or(T::CHARACTER_SIZE.height as usize + 1) as i32;
+        let position = self.menu_position.unwrap_or_else(|| Point::new(SCREEN_WIDTH as i32/2 - 100, 20));
+        let size = self.menu_size.unwrap_or_else(|| Size::new(200, SCREEN_HEIGHT - 40));
+
+        let foreground = PrimitiveStyleBuilder::new()
+            .fill_color(foreground_color)
+            .stroke_color(stroke_color)
+            .stroke_width(stroke_width)
+            .build();
+
+        let menu_foreground = Rectangle::new(position, position + size)
+            .into_styled(foreground);
+        let unselected_style = TextStyleBuilder::new(item_font)
+            .text_color(self.menu_item_color.unwrap_or(Rgb888::WHITE))
+            .background_color(background_color)
+            .build();
+        let selected_style = TextStyleBuilder::new(item_font)
+            .text_color(self.menu_selected_item_color.unwrap_or(Rgb888::YELLOW))
+            .background_color(background_color)
+            .build();
+        let title_style = TextStyleBuilder::new(title_font)
+            .background_color(background_color)
+            .text_color(self.menu_title_color.unwrap_or(Rgb888::RED))
+            .build();
+        let inset = stroke_width as i32 + TEXT_MARGIN;
+        let menu_title = Text::new(title, Point::new(menu_foreground.top_left().x+inset, menu_foreground.top_left().y+stroke_width as i32))
+            .into_styled(title_style);
+
+        let mut menu_items = [Text::new("", Point::new(0, 0)).into_styled(unselected_style); N];
+        for (item, menu_item) in menu_items.iter_mut().zip(items.iter()){
+            *item = Text::new(menu_item.label, Point::new(0, 0))
+                .into_styled(unselected_style);
+        }
+        menu_items[0].style = selected_style;
+        let mut actions = [items[0].action; N];
+        let mut kinds = [items[0].kind; N];
+        for (i, item) in items.iter().enumerate(){
+            actions[i] = item.action;
+            kinds[i] = item.kind;
+        }
+
+        // Everything under the title is the list area, which scrolls when there are more items than fit in it
+        let first_item = Point::new(menu_foreground.top_left().x+inset, menu_title.top_left().y+item_spacing);
+        let list_top_left = Point::new(menu_foreground.top_left().x+stroke_width as i32, first_item.y);
+        let list_bottom_right = menu_foreground.bottom_right() - Point::new(stroke_width as i32, stroke_width as i32);
+        let list_area = Rectangle::new(list_top_left, list_bottom_right);
+        let fitting_rows = (list_bottom_right.y - first_item.y - T::CHARACTER_SIZE.height as i32) / item_spacing + 1;
+        let visible_items = (fitting_rows.max(1) as usize).min(N);
+
+        let indicator_style = PrimitiveStyle::with_fill(unselected_style.text_color.unwrap_or(Rgb888::WHITE));
+        let indicator_x = list_bottom_right.x - TEXT_MARGIN - INDICATOR_SIZE;
+        let up_y = first_item.y;
+        let down_y = first_item.y + (visible_items as i32 - 1)*item_spacing + T::CHARACTER_SIZE.height as i32 - INDICATOR_SIZE;
+        let scroll_up_indicator = Triangle::new(Point::new(indicator_x, up_y + INDICATOR_SIZE),
+                                                Point::new(indicator_x + INDICATOR_SIZE, up_y + INDICATOR_SIZE),
+                                                Point::new(indicator_x + INDICATOR_SIZE/2, up_y))
+            .into_styled(indicator_style);
+        let scroll_down_indicator = Triangle::new(Point::new(indicator_x, down_y),
+                                                  Point::new(indicator_x + INDICATOR_SIZE, down_y),
+                                                  Point::new(indicator_x + INDICATOR_SIZE/2, down_y + INDICATOR_SIZE))
+            .into_styled(indicator_style);
+
+        let mut menu = Menu { menu_items, actions, kinds, menu_title, selected_counter: 0, menu_foreground, unselected_style,
+            selected_style, wrapping: self.menu_wrapping_enabled.unwrap_or(false), list_area, first_item,
+            item_spacing, visible_items, scroll_offset: 0, scroll_up_indicator, scroll_down_indicator};
+        menu.layout_items();
+        menu
+    }
+}
+
+impl<'a, A, T, U, const N: usize> Default for MenuBuilder<'a, A, T, U, N>
+where   A: Copy,
+        T: Font + Copy + Clone,
+        U: Font + Copy + Clone,
+{
+    fn default() -> Self{
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use embedded_graphics::fonts::Font6x12;
+    use crate::pong_controller::display::{MemoryDisplay, PIXEL_COUNT};
+
+    fn builder<const N: usize>() -> MenuBuilder<'static, u8, Font6x12, Font6x12, N>{
+        MenuBuilder::new()
+            .set_title("Test")
+            .set_item_font(Font6x12)
+            .set_title_font(Font6x12)
+    }
+
+    #[test]
+    #[should_panic(expected = "at least one item")]
+    fn a_menu_without_items_is_turned_down(){
+        let items: [MenuItem<u8>; 0] = [];
+        builder::<0>().set_items(items).build();
+    }
+
+    #[test]
+    fn items_keep_their_order_actions_and_values(){
+        let mut menu = builder::<3>()
+            .set_items([
+                MenuItem::button("One", 1),
+                MenuItem::toggle("Two", 2, true),
+                MenuItem::slider("Three", 3, 0, 10, 2, 4),
+            ])
+            .build();
+        assert_eq!(menu.selected_action(), 1);
+        menu.move_down();
+        assert_eq!((menu.selected_action(), menu.selected_value()), (2, 1));
+        menu.move_down();
+        assert_eq!((menu.selected_action(), menu.selected_value()), (3, 4));
+        assert!(menu.move_right());
+        assert_eq!(menu.selected_value(), 6);
+    }
+
+    #[test]
+    fn pickers_stand_for_the_picked_choice(){
+        const CHOICES: [(&str, u8); 3] = [("A", 10), ("B", 20), ("C", 30)];
+        let mut menu = builder::<2>()
+            .set_items([MenuItem::button("Button", 1), MenuItem::picker("Picker", &CHOICES, 1)])
+            .build();
+        menu.move_down();
+        assert_eq!(menu.selected_action(), 20);
+        assert!(menu.move_right());
+        assert_eq!(menu.selected_action(), 30);
+        menu.move_right(); // wraps around
+        assert_eq!(menu.selected_action(), 10);
+
+        menu.pick(20);
+        assert_eq!(menu.selected_action(), 20);
+        menu.pick(99); // not one of the choices
+        assert_eq!(menu.selected_action(), 20);
+    }
+
+    #[test]
+    fn plain_pairs_become_buttons(){
+        let mut menu = builder::<2>().set_items([("A", 7), ("B", 8)]).build();
+        assert!(!menu.press(), "buttons have nothing to flip");
+        menu.move_down();
+        assert_eq!(menu.selected_action(), 8);
+    }
+
+    /// Draws the menu and returns whether the arrows for scrolling up and down were drawn.
+    fn drawn_indicators<const N: usize>(menu: &Menu<u8, Font6x12, Font6x12, N>) -> (bool, bool){
+        let mut pixels = [Rgb888::BLACK; PIXEL_COUNT];
+        let mut display = MemoryDisplay::new(&mut pixels);
+        menu.draw(&mut display);
+        let drawn = |triangle: &Styled<Triangle, PrimitiveStyle<Rgb888>>|{
+            let t = triangle.primitive;
+            display.pixel((t.p1 + t.p2 + t.p3) / 3) == Some(Rgb888::WHITE) // the middle of the arrow
+        };
+        (drawn(&menu.scroll_up_indicator), drawn(&menu.scroll_down_indicator))
+    }
+
+    #[test]
+    fn scrolling_follows_the_selection_both_ways(){
+        let mut menu = builder::<6>()
+            .set_items([("A", 0), ("B", 1), ("C", 2), ("D", 3), ("E", 4), ("F", 5)])
+            .set_size(Size::new(200, 60)) // room for three items
+            .set_wrapping(true)
+            .build();
+        assert_eq!(menu.visible_items, 3);
+        assert_eq!((menu.scroll_offset, drawn_indicators(&menu)), (0, (false, true)));
+
+        let mut offsets = [0; 6];
+        for offset in offsets.iter_mut(){
+            menu.move_down();
+            *offset = menu.scroll_offset;
+        }
+        assert_eq!(offsets, [0, 0, 1, 2, 3, 0], "scrolls down one at a time and back to the top when wrapping");
+
+        menu.move_up(); // wraps around to the last item
+        assert_eq!((menu.selected_action(), menu.scroll_offset), (5, 3));
+        assert_eq!(drawn_indicators(&menu), (true, false));
+        for offset in offsets.iter_mut(){
+            menu.move_up();
+            *offset = menu.scroll_offset;
+        }
+        assert_eq!(offsets, [3, 3, 2, 1, 0, 3]);
+
+        menu.move_down();
+        menu.move_down();
+        assert_eq!((menu.selected_action(), menu.scroll_offset), (1, 0));
+        menu.move_down();
+        menu.move_down();
+        assert_eq!((menu.selected_action(), menu.scroll_offset), (3, 1));
+        assert_eq!(drawn_indicators(&menu), (true, true));
+        let row = menu.menu_items[3].primitive.position.y - menu.first_item.y;
+        assert_eq!(row, 2*menu.item_spacing, "the selected item is drawn in the last row");
+    }
+
+    #[test]
+    fn the_background_colour_goes_behind_the_text_only(){
+        let menu = builder::<1>()
+            .set_items([("Item", 0)])
+            .set_foreground_color(Rgb888::CYAN)
+            .set_background_color(Rgb888::BLUE)
+            .build();
+        let mut pixels = [Rgb888::BLACK; PIXEL_COUNT];
+        let mut display = MemoryDisplay::new(&mut pixels);
+        menu.draw(&mut display);
+
+        let title = Rectangle::new(menu.menu_title.top_left(), menu.menu_title.bottom_right());
+        let behind_title = (title.top_left.y..title.bottom_right.y)
+            .flat_map(|y| (title.top_left.x..title.bottom_right.x).map(move |x| Point::new(x, y)))
+            .filter(|&point| display.pixel(point) == Some(Rgb888::BLUE))
+            .count();
+        assert!(behind_title > 0, "the title is drawn on the background colour");
+        let corner = menu.menu_foreground.bottom_right() - Point::new(1, 1);
+        assert_eq!(display.pixel(corner), Some(Rgb888::CYAN), "the rest of the box keeps its own colour");
+    }
+}
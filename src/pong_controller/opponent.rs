@@ -1,16 +1,21 @@
 use crate::pong_controller::paddle::{Paddle, PaddleBuilder, PaddleMovementSpeed};
-use crate::pong_controller::ball::BallBounds;
-use psp::embedded_graphics::Framebuffer;
+use crate::pong_controller::ball::{Ball, BallBounds};
+use crate::pong_controller::score::Side;
+use crate::pong_controller::{SCREEN_WIDTH, SCREEN_HEIGHT};
+use embedded_graphics::DrawTarget;
+use embedded_graphics::pixelcolor::Rgb888;
 
-const DETECTION_X_BASE:i32 = psp::SCREEN_WIDTH as i32 - psp::SCREEN_WIDTH as i32/3; // By default it starts moving towards the ball when it is less than 1/3rd of the screen away.
+const DETECTION_X_BASE:i32 = SCREEN_WIDTH as i32 - SCREEN_WIDTH as i32/3; // By default it starts moving towards the ball when it is less than 1/3rd of the screen away.
 
 pub struct Opponent{
     paddle: Paddle,
     difficulty: OpponentDifficulty,
     detection_x: i32,
+    side: Side, // which side of the screen its paddle is on
+    ai: OpponentAi,
 }
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, PartialEq)]
 pub enum OpponentDifficulty{
     VeryEasy = 0,
     Easy = 1,
@@ -19,42 +24,252 @@ pub enum OpponentDifficulty{
     VeryHard = 4
 }
 
+/// How the opponent decides where to move its paddle.
+#[derive(Copy, Clone, PartialEq)]
+pub enum OpponentAi{
+    Chase, // follows the ball up and down once it comes close enough
+    Predict, // works out where the ball will reach its paddle, bounces off the walls included, and waits there
+}
+
 impl Opponent{
 
     pub fn new(difficulty: OpponentDifficulty) -> Self{
+        Self::on_side(Side::Opponent, difficulty)
+    }
+
+    /// Creates an opponent that plays on `side` of the screen.
+    pub fn on_side(side: Side, difficulty: OpponentDifficulty) -> Self{
+        let x = match side{
+            Side::Player => 10,
+            Side::Opponent => SCREEN_WIDTH as i32 - 15,
+        };
+        let paddle = PaddleBuilder::new()
+            .set_default_dimensions()
+            .set_speed(Self::speed(difficulty))
+            .set_x(x)
+            .set_y(SCREEN_HEIGHT as i32/2)
+            .build();
+
+        Self {paddle, difficulty, detection_x: Self::detection_x(difficulty), side, ai: OpponentAi::Chase}
+    }
 
-        let speed = match difficulty {
+    /// Returns how fast the paddle moves at a difficulty.
+    fn speed(difficulty: OpponentDifficulty) -> PaddleMovementSpeed{
+        match difficulty {
             OpponentDifficulty::VeryEasy => PaddleMovementSpeed::Slow,
             OpponentDifficulty::Easy => PaddleMovementSpeed::Normal,
             OpponentDifficulty::Normal => PaddleMovementSpeed::Normal,
             OpponentDifficulty::Hard => PaddleMovementSpeed::Fast,
             OpponentDifficulty::VeryHard => PaddleMovementSpeed::VeryFast
-        };
+        }
+    }
 
-        let paddle = PaddleBuilder::new()
-            .set_default_dimensions()
-            .set_speed(speed)
-            .set_x(psp::SCREEN_WIDTH as i32 - 15)
-            .set_y(psp::SCREEN_HEIGHT as i32/2)
-            .build();
+    /// Returns the detection location (based on difficulty)
+    fn detection_x(difficulty: OpponentDifficulty) -> i32{
+        DETECTION_X_BASE - 25*(difficulty as i32)
+    }
+
+    /// Changes how hard the opponent is to beat, leaving its paddle where it is.
+    pub fn set_difficulty(&mut self, difficulty: OpponentDifficulty){
+        self.difficulty = difficulty;
+        self.detection_x = Self::detection_x(difficulty);
+        self.paddle.set_speed(Self::speed(difficulty));
+    }
 
-        let detection_x = DETECTION_X_BASE - 25*(difficulty as i32); // sets up the detection location (based on difficulty)
-        Self {paddle, difficulty, detection_x}
+    /// Changes how the opponent decides where to move its paddle.
+    pub fn set_ai(&mut self, ai: OpponentAi){
+        self.ai = ai;
     }
 
-    /// Simple AI that moves its paddle in an attempt to save the ball.
-    pub fn step_opponent(&mut self, ball: &BallBounds, disp:&mut Framebuffer){
-        if ball.right >= self.detection_x{ // checks to see if the ball is able to be detected
+    /// Moves its paddle in an attempt to save the ball.
+    pub fn step_opponent(&mut self, ball: &Ball){
+        self.paddle.stay();
+        match self.ai{
+            OpponentAi::Chase => self.chase(ball.get_bounds()),
+            OpponentAi::Predict => self.predict(ball),
+        }
+    }
+
+    /// Simple AI that follows the ball once it is past the detection location.
+    fn chase(&mut self, ball: &BallBounds){
+        if self.detects(ball){ // checks to see if the ball is able to be detected
             if ball.top < self.paddle.get_bounds().top{ // If the top of the ball is above the paddle
-                self.paddle.move_up(disp);
+                self.paddle.move_up();
             } else if ball.bottom > self.paddle.get_bounds().bottom{ // If the ball is below the paddle
-                self.paddle.move_down(disp);
+                self.paddle.move_down();
             }
         }
     }
 
-    /// Checks to see if a given point is within the paddle.
-    pub fn contains(&mut self, x:i32, y:i32) -> bool{
-        self.paddle.contains(x,y, false)
+    /// Checks to see if the ball is past the detection location, which is mirrored for a paddle on the left.
+    fn detects(&self, ball: &BallBounds) -> bool{
+        match self.side{
+            Side::Player => ball.left <= SCREEN_WIDTH as i32 - self.detection_x,
+            Side::Opponent => ball.right >= self.detection_x,
+        }
+    }
+
+    /// AI that heads for where the ball will reach its paddle once it is past the detection location,
+    /// and back to the middle while the ball is heading away.
+    fn predict(&mut self, ball: &Ball){
+        let bounds = self.paddle.get_bounds();
+        let half_height = (bounds.bottom - bounds.top) as f32/2.0;
+        let center = bounds.top as f32 + half_height;
+        let target = match self.intercept(ball){
+            Some(y) if self.detects(ball.get_bounds()) => y,
+            Some(_) => center, // waits where it is until it can see the ball
+            None => SCREEN_HEIGHT as f32/2.0,
+        };
+        let target = target
+            .clamp(half_height, SCREEN_HEIGHT as f32 - half_height); // keeps the paddle on the screen
+        let margin = Self::speed(self.difficulty) as i32 as f32/2.0; // closer than this and another move would overshoot
+
+        if target < center - margin{
+            self.paddle.move_up();
+        } else if target > center + margin{
+            self.paddle.move_down();
+        }
+    }
+
+    /// Returns how far down the screen the center of the ball will be when it reaches the paddle, following
+    /// its bounces off the top and bottom of the screen, or `None` if it is heading away.
+    fn intercept(&self, ball: &Ball) -> Option<f32>{
+        let position = ball.get_position();
+        let velocity = ball.get_velocity();
+        let radius = ball.get_radius();
+        let bounds = self.paddle.get_bounds();
+        let face = match self.side{ // where the center of the ball is when it touches the paddle
+            Side::Player if velocity.x < 0.0 => bounds.right as f32 + radius,
+            Side::Opponent if velocity.x > 0.0 => bounds.left as f32 - radius,
+            _ => return None,
+        };
+        let updates = ((face - position.x) / velocity.x).max(0.0);
+
+        // The center of the ball stays between `radius` and `SCREEN_HEIGHT - radius`, bouncing between them,
+        // so its path repeats every time it has gone there and back
+        let span = SCREEN_HEIGHT as f32 - 2.0*radius;
+        let mut y = libm::fmodf(position.y + velocity.y*updates - radius, 2.0*span);
+        if y < 0.0{
+            y += 2.0*span;
+        }
+        Some(radius + if y > span { 2.0*span - y } else { y })
+    }
+
+    /// Keeps the opponent's paddle where it is for this update
+    pub fn stay(&mut self){
+        self.paddle.stay();
     }
-}
\ No newline at end of file
+
+    /// Draws the opponent's paddle
+    pub fn draw<D: DrawTarget<Rgb888>>(&self, disp: &mut D, alpha: f32){
+        self.paddle.draw(disp, alpha);
+    }
+
+    /// Returns how hard the opponent is to beat.
+    pub fn get_difficulty(&self) -> OpponentDifficulty{
+        self.difficulty
+    }
+
+    /// Returns how the opponent decides where to move its paddle.
+    pub fn get_ai(&self) -> OpponentAi{
+        self.ai
+    }
+
+    /// Gets an immutable reference to the opponent's paddle.
+    pub fn get_paddle(&self) -> &Paddle{
+        &self.paddle
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pong_controller::vector::Vec2;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha20Rng;
+
+    const RADIUS: f32 = 10.0;
+
+    fn ball(x: f32, y: f32, velocity: Vec2) -> Ball{
+        let mut rng = ChaCha20Rng::seed_from_u64(1);
+        Ball::new(x as i32, y as i32, RADIUS as u32, 0.0, &mut rng).with_velocity(velocity)
+    }
+
+    fn bounds(left: i32, right: i32) -> BallBounds{
+        BallBounds {top: 126, bottom: 146, left, right}
+    }
+
+    /// Returns where the center of the ball is when it touches the face of the right paddle.
+    fn right_face(opponent: &Opponent) -> f32{
+        opponent.get_paddle().get_bounds().left as f32 - RADIUS
+    }
+
+    fn paddle_center(opponent: &Opponent) -> i32{
+        let bounds = opponent.get_paddle().get_bounds();
+        (bounds.top + bounds.bottom)/2
+    }
+
+    #[test]
+    fn a_ball_going_straight_across_is_met_where_it_is(){
+        let opponent = Opponent::new(OpponentDifficulty::Normal);
+        let face = right_face(&opponent);
+        assert_eq!(opponent.intercept(&ball(face - 200.0, 136.0, Vec2::new(4.0, 0.0))), Some(136.0));
+    }
+
+    #[test]
+    fn follows_the_ball_off_a_wall(){
+        let opponent = Opponent::new(OpponentDifficulty::Normal);
+        let face = right_face(&opponent);
+        // 50 updates take it 100 pixels up from 100, which is 10 past where it bounces off the top
+        assert_eq!(opponent.intercept(&ball(face - 200.0, 100.0, Vec2::new(4.0, -2.0))), Some(20.0));
+    }
+
+    #[test]
+    fn follows_the_ball_off_several_walls(){
+        let opponent = Opponent::new(OpponentDifficulty::Normal);
+        let face = right_face(&opponent);
+        // 200 updates take it 1200 pixels down from 136, bouncing off the bottom, top, bottom, top and bottom
+        let y = opponent.intercept(&ball(face - 400.0, 136.0, Vec2::new(2.0, 6.0))).unwrap();
+        assert!((y - 196.0).abs() < 1e-3, "met at {} instead of 196", y);
+    }
+
+    #[test]
+    fn a_ball_heading_away_is_not_met(){
+        let opponent = Opponent::new(OpponentDifficulty::Normal);
+        assert!(opponent.intercept(&ball(240.0, 136.0, Vec2::new(-4.0, 1.0))).is_none());
+
+        let left = Opponent::on_side(Side::Player, OpponentDifficulty::Normal);
+        assert!(left.intercept(&ball(240.0, 136.0, Vec2::new(4.0, 1.0))).is_none());
+        let face = left.get_paddle().get_bounds().right as f32 + RADIUS;
+        assert_eq!(left.intercept(&ball(face + 200.0, 136.0, Vec2::new(-4.0, 0.0))), Some(136.0));
+    }
+
+    #[test]
+    fn the_paddle_goes_back_to_the_middle_while_the_ball_heads_away(){
+        let mut opponent = Opponent::new(OpponentDifficulty::Normal);
+        opponent.set_ai(OpponentAi::Predict);
+        for _ in 0..10{
+            opponent.paddle.move_down();
+        }
+        assert!(paddle_center(&opponent) > SCREEN_HEIGHT as i32/2 + 40);
+
+        let away = ball(240.0, 40.0, Vec2::new(-4.0, 0.0));
+        for _ in 0..30{
+            opponent.step_opponent(&away);
+        }
+        let margin = Opponent::speed(OpponentDifficulty::Normal) as i32/2 + 1;
+        assert!((paddle_center(&opponent) - SCREEN_HEIGHT as i32/2).abs() <= margin,
+            "the paddle stopped at {}", paddle_center(&opponent));
+    }
+
+    #[test]
+    fn the_left_paddle_sees_the_ball_from_the_mirrored_distance(){
+        let left = Opponent::on_side(Side::Player, OpponentDifficulty::Normal);
+        let right = Opponent::new(OpponentDifficulty::Normal);
+        let reach = SCREEN_WIDTH as i32 - right.detection_x; // how far out from its own side each one sees
+
+        assert!(right.detects(&bounds(SCREEN_WIDTH as i32 - reach - 20, SCREEN_WIDTH as i32 - reach)));
+        assert!(!right.detects(&bounds(SCREEN_WIDTH as i32 - reach - 21, SCREEN_WIDTH as i32 - reach - 1)));
+        assert!(left.detects(&bounds(reach, reach + 20)));
+        assert!(!left.detects(&bounds(reach + 1, reach + 21)));
+    }
+}
@@ -1,14 +1,15 @@
 use embedded_graphics::prelude::{Point, Primitive, Transform, Dimensions};
-use psp::embedded_graphics::Framebuffer;
+use embedded_graphics::DrawTarget;
 use embedded_graphics::primitives::Rectangle;
 use embedded_graphics::style::{Styled, PrimitiveStyle, PrimitiveStyleBuilder};
 use embedded_graphics::pixelcolor::Rgb888;
 use embedded_graphics::pixelcolor::RgbColor;
 use embedded_graphics::drawable::Drawable;
+use crate::pong_controller::timestep::lerp;
 
 pub struct Paddle{
     rect: Styled<Rectangle, PrimitiveStyle<Rgb888>>,
-    background: Styled<Rectangle, PrimitiveStyle<Rgb888>>,
+    previous: Point, // top left of the paddle before the last move
     bounds: PaddleBounds,
     speed_up: Point, // Speed of the paddle moving up the screen
     speed_down: Point, // Speed of the paddle moving down the screen
@@ -82,6 +83,12 @@ impl PaddleBuilder{
     }
 }
 
+impl Default for PaddleBuilder{
+    fn default() -> Self{
+        Self::new()
+    }
+}
+
 pub struct PaddleBounds{
     pub top: i32,
     pub bottom: i32,
@@ -95,15 +102,10 @@ impl Paddle {
         let style = PrimitiveStyleBuilder::new()
             .fill_color(Rgb888::WHITE)
             .build();
-        let background_style = PrimitiveStyleBuilder::new()
-            .fill_color(Rgb888::BLACK)
-            .build();
         let point1 = Point::new(x,y);
         let point2 = Point::new(x+thickness as i32,y+height as i32);
         let rect = Rectangle::new(point1,point2)
             .into_styled(style);
-        let background = Rectangle::new(point1, point2)
-            .into_styled(background_style);
 
         let bounds = PaddleBounds {
             top: rect.primitive.top_left().y,
@@ -113,7 +115,13 @@ impl Paddle {
 
         let speed_up = Point::new(0,-(speed as i32));
         let speed_down = Point::new(0,speed as i32);
-        Self {rect, background, bounds, speed_up, speed_down}
+        Self {rect, previous: point1, bounds, speed_up, speed_down}
+    }
+
+    /// Changes how fast the paddle moves
+    pub fn set_speed(&mut self, speed: PaddleMovementSpeed){
+        self.speed_up = Point::new(0,-(speed as i32));
+        self.speed_down = Point::new(0,speed as i32);
     }
 
     /// Updates the now-current bounds of the paddle.
@@ -124,45 +132,43 @@ impl Paddle {
         self.bounds.right = self.rect.primitive.bottom_right().x;
     }
 
-    /// Redraws the paddle
-    pub fn redraw(&mut self, disp: &mut Framebuffer){
-        self.rect.draw(disp);
+    /// Draws the paddle `alpha` of the way between where it was before its last move and where it is now.
+    pub fn draw<D: DrawTarget<Rgb888>>(&self, disp: &mut D, alpha: f32){
+        let top_left = lerp(self.previous, self.rect.primitive.top_left, alpha);
+        let mut rect = self.rect;
+        rect.translate_mut(top_left - rect.primitive.top_left);
+        let _ = rect.draw(disp);
+    }
+
+    /// Keeps the paddle where it is for this update
+    pub fn stay(&mut self){
+        self.previous = self.rect.primitive.top_left;
     }
 
     /// Moves the paddle up
-    pub fn move_up(&mut self, disp: &mut Framebuffer){
-        self.background.draw(disp);
+    pub fn move_up(&mut self){
+        self.stay();
         self.rect.translate_mut(self.speed_up);
-        self.background.translate_mut(self.speed_up);
-        self.rect.draw(disp);
         self.update_bounds();
     }
 
     /// Moves the paddle down
-    pub fn move_down(&mut self, disp: &mut Framebuffer){
-        self.background.draw(disp);
+    pub fn move_down(&mut self){
+        self.stay();
         self.rect.translate_mut(self.speed_down);
-        self.background.translate_mut(self.speed_down);
-        self.rect.draw(disp);
         self.update_bounds();
     }
-    /// Checks to see if a given point is within the paddle.
-    pub fn contains(&self, x:i32, y:i32, is_player:bool) -> bool {
-        if is_player{
-            if  x < self.rect.primitive.bottom_right.x &&
-                y > self.rect.primitive.top_left.y &&
-                y < self.rect.primitive.bottom_right.y{
-                return true
-            }
-        } else {
-            if  x > self.rect.primitive.top_left.x &&
-                y > self.rect.primitive.top_left.y &&
-                y < self.rect.primitive.bottom_right.y{
-                return true
-            }
-        }
 
-        false
+    /// Returns where `y` is along the paddle, from -1 at its top edge to 1 at its bottom edge.
+    pub fn hit_offset(&self, y: f32) -> f32 {
+        let half_height = (self.bounds.bottom - self.bounds.top) as f32 / 2.0;
+        let center = self.bounds.top as f32 + half_height;
+        ((y - center) / half_height).clamp(-1.0, 1.0)
+    }
+
+    /// Returns how many pixels the paddle moved down the screen during the last update.
+    pub fn get_velocity(&self) -> i32 {
+        self.rect.primitive.top_left.y - self.previous.y
     }
 
     pub fn get_bounds(&self) -> &PaddleBounds { &self.bounds }
@@ -0,0 +1,154 @@
+/// One of the two sides of the screen.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Side {
+    Player, // the left paddle
+    Opponent, // the right paddle
+}
+
+impl Side {
+    /// Returns the side across from this one.
+    pub fn other(self) -> Side{
+        match self{
+            Side::Player => Side::Opponent,
+            Side::Opponent => Side::Player,
+        }
+    }
+}
+
+/// How a match is won.
+#[derive(Copy, Clone)]
+pub struct MatchRules {
+    pub points_to_win: u32, // points needed to win a set
+    pub win_by_two: bool, // a set only ends once someone is two points ahead
+    pub best_of: u32, // how many sets the match can last, so the winner needs more than half of them
+}
+
+impl MatchRules {
+    pub const fn new(points_to_win: u32, win_by_two: bool, best_of: u32) -> Self{
+        Self {points_to_win, win_by_two, best_of}
+    }
+
+    /// Returns how many sets have to be won to win the match.
+    pub fn sets_to_win(&self) -> u32{
+        self.best_of/2 + 1
+    }
+
+    /// Returns who has won a set with `points` to `other_points`, if anyone.
+    fn set_winner(&self, points: u32, other_points: u32) -> Option<Side>{
+        let lead = if self.win_by_two { 2 } else { 1 };
+        if points >= self.points_to_win && points >= other_points + lead{
+            Some(Side::Player)
+        } else if other_points >= self.points_to_win && other_points >= points + lead{
+            Some(Side::Opponent)
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct Score {
+    pub player: u32, // points in the current set
+    pub opponent: u32,
+    pub player_sets: u32, // sets won so far
+    pub opponent_sets: u32,
+}
+
+impl Score {
+    pub fn new() -> Self{
+        Self::default()
+    }
+
+    /// Gives `side` a point, ending the set if it was won. Returns who won the match, if anyone has.
+    /// The points of the last set are kept when the match is over so they can be shown.
+    pub fn add_point(&mut self, side: Side, rules: &MatchRules) -> Option<Side>{
+        match side{
+            Side::Player => self.player += 1,
+            Side::Opponent => self.opponent += 1,
+        }
+
+        let sets = match rules.set_winner(self.player, self.opponent){
+            Some(Side::Player) => { self.player_sets += 1; self.player_sets },
+            Some(Side::Opponent) => { self.opponent_sets += 1; self.opponent_sets },
+            None => return None,
+        };
+        if sets >= rules.sets_to_win(){
+            return Some(side);
+        }
+        self.player = 0;
+        self.opponent = 0;
+        None
+    }
+
+    /// Starts the match over.
+    pub fn reset(&mut self){
+        *self = Self::new();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Gives `side` a point `times` times, returning who won the match with the last one, if anyone did.
+    fn add_points(score: &mut Score, side: Side, times: u32, rules: &MatchRules) -> Option<Side>{
+        (0..times).fold(None, |_, _| score.add_point(side, rules))
+    }
+
+    #[test]
+    fn a_deuce_goes_on_until_someone_is_two_ahead(){
+        let rules = MatchRules::new(11, true, 1);
+        let mut score = Score::new();
+        add_points(&mut score, Side::Player, 10, &rules);
+        add_points(&mut score, Side::Opponent, 10, &rules);
+        assert_eq!(score.add_point(Side::Player, &rules), None); // 11-10
+        assert_eq!(score.add_point(Side::Opponent, &rules), None); // 11-11
+        assert_eq!(score.add_point(Side::Player, &rules), None); // 12-11
+        assert_eq!(score.add_point(Side::Player, &rules), Some(Side::Player)); // 13-11
+        assert_eq!((score.player, score.opponent), (13, 11)); // kept to be shown
+    }
+
+    #[test]
+    fn a_deuce_can_be_won_at_twelve_to_ten(){
+        let rules = MatchRules::new(11, true, 1);
+        let mut score = Score::new();
+        add_points(&mut score, Side::Player, 10, &rules);
+        add_points(&mut score, Side::Opponent, 10, &rules);
+        assert_eq!(add_points(&mut score, Side::Opponent, 2, &rules), Some(Side::Opponent));
+        assert_eq!((score.player, score.opponent), (10, 12));
+    }
+
+    #[test]
+    fn without_win_by_two_a_set_ends_at_exactly_the_points_to_win(){
+        let rules = MatchRules::new(11, false, 1);
+        let mut score = Score::new();
+        add_points(&mut score, Side::Player, 10, &rules);
+        add_points(&mut score, Side::Opponent, 10, &rules);
+        assert_eq!(score.add_point(Side::Player, &rules), Some(Side::Player));
+        assert_eq!((score.player, score.opponent), (11, 10));
+    }
+
+    #[test]
+    fn winning_a_set_starts_the_next_one_from_nothing(){
+        let rules = MatchRules::new(5, true, 3);
+        let mut score = Score::new();
+        add_points(&mut score, Side::Opponent, 3, &rules);
+        assert_eq!(add_points(&mut score, Side::Player, 5, &rules), None);
+        assert_eq!((score.player, score.opponent), (0, 0));
+        assert_eq!((score.player_sets, score.opponent_sets), (1, 0));
+    }
+
+    #[test]
+    fn a_best_of_three_ends_after_two_sets(){
+        let rules = MatchRules::new(5, true, 3);
+        let mut score = Score::new();
+        assert_eq!(add_points(&mut score, Side::Player, 5, &rules), None);
+        assert_eq!(add_points(&mut score, Side::Opponent, 5, &rules), None);
+        assert_eq!(add_points(&mut score, Side::Opponent, 5, &rules), Some(Side::Opponent));
+        assert_eq!((score.player_sets, score.opponent_sets), (1, 2));
+
+        let mut straight_sets = Score::new();
+        assert_eq!(add_points(&mut straight_sets, Side::Player, 5, &rules), None);
+        assert_eq!(add_points(&mut straight_sets, Side::Player, 5, &rules), Some(Side::Player));
+    }
+}
@@ -0,0 +1,98 @@
+use embedded_graphics::prelude::Point;
+
+/// Splits the time passing on a clock into fixed-size steps, so the game runs at the same speed no
+/// matter how long a frame takes to draw.
+pub struct FixedTimestep {
+    interval: u64, // microseconds per step
+    accumulator: u64, // time that has passed but hasn't been stepped through yet
+    last: u64,
+    max_steps: u32,
+}
+
+impl FixedTimestep {
+    /// Creates a timestep of `interval` microseconds starting at `now`. At most `max_steps` are
+    /// caught up on per call to `advance`, anything more is dropped.
+    pub fn new(interval: u64, now: u64, max_steps: u32) -> Self {
+        Self { interval, accumulator: 0, last: now, max_steps }
+    }
+
+    /// Adds the time passed since the last call and returns how many steps should be run.
+    pub fn advance(&mut self, now: u64) -> u32 {
+        self.accumulator += now.saturating_sub(self.last);
+        self.last = now;
+
+        let steps = self.accumulator / self.interval;
+        if steps > self.max_steps as u64 { // Too far behind to catch up, so the backlog is dropped
+            self.accumulator %= self.interval;
+            return self.max_steps;
+        }
+        self.accumulator -= steps * self.interval;
+        steps as u32
+    }
+
+    /// Returns how far along the next step the time is, from 0 up to 1.
+    pub fn alpha(&self) -> f32 {
+        self.accumulator as f32 / self.interval as f32
+    }
+}
+
+/// Returns the point `alpha` of the way from `from` to `to`.
+pub fn lerp(from: Point, to: Point, alpha: f32) -> Point {
+    let offset = to - from;
+    from + Point::new((offset.x as f32 * alpha) as i32, (offset.y as f32 * alpha) as i32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pong_controller::clock::{Clock, ManualClock};
+
+    const INTERVAL: u64 = 50_000;
+
+    #[test]
+    fn steps_are_run_for_every_whole_interval() {
+        let clock = ManualClock::new(1_000);
+        let mut timestep = FixedTimestep::new(INTERVAL, clock.now(), 5);
+        assert_eq!(timestep.advance(clock.now()), 0);
+
+        clock.advance(30_000);
+        assert_eq!(timestep.advance(clock.now()), 0);
+        clock.advance(30_000); // 60ms have passed in total
+        assert_eq!(timestep.advance(clock.now()), 1);
+        clock.advance(115_000); // the 10ms left over plus 115ms
+        assert_eq!(timestep.advance(clock.now()), 2);
+        assert!((timestep.alpha() - 0.5).abs() < 1e-6);
+    }
+
+    #[test]
+    fn a_long_stall_is_capped_and_dropped() {
+        let clock = ManualClock::new(0);
+        let mut timestep = FixedTimestep::new(INTERVAL, clock.now(), 5);
+        clock.advance(2_000_000 + 20_000); // 40 steps behind
+        assert_eq!(timestep.advance(clock.now()), 5);
+        assert!((timestep.alpha() - 0.4).abs() < 1e-6, "only the part of a step left over is kept");
+
+        clock.advance(30_000);
+        assert_eq!(timestep.advance(clock.now()), 1, "the steps that were dropped aren't caught up on later");
+    }
+
+    #[test]
+    fn alpha_stays_within_a_step() {
+        let clock = ManualClock::new(0);
+        let mut timestep = FixedTimestep::new(INTERVAL, clock.now(), 5);
+        for &elapsed in &[0, 1, 49_999, 50_000, 12_345, 99_999, 1_000_000, 7, 250_001] {
+            clock.advance(elapsed);
+            timestep.advance(clock.now());
+            let alpha = timestep.alpha();
+            assert!((0.0..1.0).contains(&alpha), "alpha was {} after {}us", alpha, elapsed);
+        }
+    }
+
+    #[test]
+    fn a_clock_going_backwards_runs_no_steps() {
+        let clock = ManualClock::new(100_000);
+        let mut timestep = FixedTimestep::new(INTERVAL, clock.now(), 5);
+        clock.set(0);
+        assert_eq!(timestep.advance(clock.now()), 0);
+    }
+}
@@ -0,0 +1,80 @@
+use core::ops::{Add, AddAssign, Mul, Sub};
+use embedded_graphics::prelude::Point;
+
+/// A 2D vector with sub-pixel precision, used for positions and velocities.
+#[derive(Copy, Clone, PartialEq, Debug, Default)]
+pub struct Vec2 {
+    pub x: f32,
+    pub y: f32,
+}
+
+impl Vec2 {
+    pub const fn new(x: f32, y: f32) -> Self {
+        Self { x, y }
+    }
+
+    /// Creates a vector of a given length pointing `angle` radians clockwise from the positive x axis.
+    pub fn from_angle(angle: f32, length: f32) -> Self {
+        Self::new(libm::cosf(angle) * length, libm::sinf(angle) * length)
+    }
+
+    pub fn length(self) -> f32 {
+        libm::sqrtf(self.x * self.x + self.y * self.y)
+    }
+
+    /// Returns the vector scaled to a new length, keeping its direction.
+    pub fn with_length(self, length: f32) -> Self {
+        let current = self.length();
+        if current == 0.0 {
+            return self;
+        }
+        self * (length / current)
+    }
+
+    /// Rounds the vector to the nearest pixel.
+    pub fn to_point(self) -> Point {
+        Point::new(libm::roundf(self.x) as i32, libm::roundf(self.y) as i32)
+    }
+
+    /// Returns the point `alpha` of the way from `self` to `to`.
+    pub fn lerp(self, to: Vec2, alpha: f32) -> Self {
+        self + (to - self) * alpha
+    }
+}
+
+impl From<Point> for Vec2 {
+    fn from(point: Point) -> Self {
+        Self::new(point.x as f32, point.y as f32)
+    }
+}
+
+impl Add for Vec2 {
+    type Output = Vec2;
+
+    fn add(self, other: Vec2) -> Vec2 {
+        Vec2::new(self.x + other.x, self.y + other.y)
+    }
+}
+
+impl AddAssign for Vec2 {
+    fn add_assign(&mut self, other: Vec2) {
+        self.x += other.x;
+        self.y += other.y;
+    }
+}
+
+impl Sub for Vec2 {
+    type Output = Vec2;
+
+    fn sub(self, other: Vec2) -> Vec2 {
+        Vec2::new(self.x - other.x, self.y - other.y)
+    }
+}
+
+impl Mul<f32> for Vec2 {
+    type Output = Vec2;
+
+    fn mul(self, scale: f32) -> Vec2 {
+        Vec2::new(self.x * scale, self.y * scale)
+    }
+}
@@ -0,0 +1,235 @@
+//! Renders frames into a `MemoryDisplay` and compares them with the reference images in `tests/snapshots`.
+//! Run with `UPDATE_SNAPSHOTS=1` to write the reference images again after an intended change to the visuals.
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use embedded_graphics::fonts::Font12x16;
+use embedded_graphics::pixelcolor::{Rgb888, RgbColor};
+use embedded_graphics::prelude::{Point, Size};
+use psp_pong::pong_controller::ball::Ball;
+use psp_pong::pong_controller::display::{MemoryDisplay, PIXEL_COUNT};
+use psp_pong::pong_controller::input::Buttons;
+use psp_pong::pong_controller::menu::{MenuBuilder, MenuItem};
+use psp_pong::pong_controller::paddle::{PaddleBuilder, PaddleMovementSpeed};
+use psp_pong::pong_controller::vector::Vec2;
+use psp_pong::{GameState, PongController, SCREEN_HEIGHT, SCREEN_WIDTH};
+use rand::SeedableRng;
+use rand_chacha::ChaCha20Rng;
+
+const SEED: u64 = 7;
+
+/// Encodes a frame as a binary PPM image.
+fn to_ppm(display: &MemoryDisplay) -> Vec<u8> {
+    let mut ppm = format!("P6\n{} {}\n255\n", SCREEN_WIDTH, SCREEN_HEIGHT).into_bytes();
+    for pixel in display.pixels() {
+        ppm.extend_from_slice(&[pixel.r(), pixel.g(), pixel.b()]);
+    }
+    ppm
+}
+
+fn snapshot_path(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests").join("snapshots").join(format!("{}.ppm", name))
+}
+
+/// Fails if the frame differs from the reference image called `name`. The frame that was drawn instead is
+/// written to the system's temporary directory so the two can be compared.
+fn assert_snapshot(name: &str, display: &MemoryDisplay) {
+    let actual = to_ppm(display);
+    let path = snapshot_path(name);
+    if env::var_os("UPDATE_SNAPSHOTS").is_some() {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, &actual).unwrap();
+        return;
+    }
+
+    let expected = fs::read(&path)
+        .unwrap_or_else(|_| panic!("{} is missing, run the tests with UPDATE_SNAPSHOTS=1 to create it", path.display()));
+    if expected != actual {
+        let actual_path = env::temp_dir().join(format!("{}.ppm", name));
+        fs::write(&actual_path, &actual).unwrap();
+        let different = expected.iter().zip(&actual).filter(|(a, b)| a != b).count();
+        panic!("{} does not match {} ({} bytes differ), the frame that was drawn is at {}",
+            name, path.display(), different, actual_path.display());
+    }
+}
+
+/// Returns the memory for a `MemoryDisplay`, filled with black.
+fn screen_buffer() -> Vec<Rgb888> {
+    vec![Rgb888::BLACK; PIXEL_COUNT]
+}
+
+fn new_game(buffer: &mut [Rgb888]) -> PongController<MemoryDisplay<'_>> {
+    PongController::new(MemoryDisplay::new(buffer), 60, SEED)
+}
+
+/// Runs one update per entry in `frames`, with a frame rendered after each.
+fn play(pong: &mut PongController<MemoryDisplay<'_>>, frames: &[Buttons]) {
+    for &buttons in frames {
+        pong.step(buttons);
+        pong.render(1.0);
+    }
+}
+
+/// Presses a button and lets go of it on the next update.
+fn press(pong: &mut PongController<MemoryDisplay<'_>>, button: Buttons) {
+    play(pong, &[button, Buttons::empty()]);
+}
+
+/// Starts a one player match and waits for the serve.
+fn start_match(buffer: &mut [Rgb888]) -> PongController<MemoryDisplay<'_>> {
+    let mut pong = new_game(buffer);
+    play(&mut pong, &[Buttons::empty()]);
+    press(&mut pong, Buttons::CROSS);
+    while let GameState::Serve(_) = pong.state() {
+        play(&mut pong, &[Buttons::empty()]);
+    }
+    pong
+}
+
+#[test]
+fn title_screen() {
+    let mut buffer = screen_buffer();
+    let mut pong = new_game(&mut buffer);
+    play(&mut pong, &[Buttons::empty()]);
+    assert_snapshot("title_screen", pong.display());
+}
+
+#[test]
+fn serve_countdown() {
+    let mut buffer = screen_buffer();
+    let mut pong = new_game(&mut buffer);
+    play(&mut pong, &[Buttons::empty()]);
+    press(&mut pong, Buttons::CROSS);
+    assert!(matches!(pong.state(), GameState::Serve(_)));
+    assert_snapshot("serve_countdown", pong.display());
+}
+
+#[test]
+fn rally() {
+    let mut buffer = screen_buffer();
+    let mut pong = start_match(&mut buffer);
+    play(&mut pong, &[Buttons::UP; 30]);
+    assert_eq!(pong.state(), GameState::Playing);
+    assert_snapshot("rally", pong.display());
+}
+
+#[test]
+fn pause_screen() {
+    let mut buffer = screen_buffer();
+    let mut pong = start_match(&mut buffer);
+    play(&mut pong, &[Buttons::DOWN; 10]);
+    press(&mut pong, Buttons::START);
+    assert_eq!(pong.state(), GameState::Paused);
+    assert_snapshot("pause_screen", pong.display());
+}
+
+#[test]
+fn pause_screen_between_updates() {
+    let mut buffer = screen_buffer();
+    let mut pong = start_match(&mut buffer);
+    play(&mut pong, &[Buttons::DOWN; 10]);
+    press(&mut pong, Buttons::START);
+    pong.render(0.25);
+    assert_snapshot("pause_screen", pong.display()); // nothing is drawn partway through its last move
+}
+
+#[test]
+fn point_scored_between_updates() {
+    let mut buffer = screen_buffer();
+    let mut pong = start_match(&mut buffer);
+    for _ in 0..10_000 {
+        if let GameState::PointScored(_) = pong.state() {
+            break;
+        }
+        pong.step(Buttons::DOWN);
+    }
+    assert!(matches!(pong.state(), GameState::PointScored(_)));
+
+    pong.render(1.0);
+    let finished = to_ppm(pong.display());
+    pong.render(0.25);
+    assert!(finished == to_ppm(pong.display()), "the ball or a paddle was drawn partway through its last move");
+    assert_snapshot("point_scored", pong.display());
+}
+
+#[test]
+fn settings_menu() {
+    let mut buffer = screen_buffer();
+    let mut pong = start_match(&mut buffer);
+    press(&mut pong, Buttons::START);
+    press(&mut pong, Buttons::SELECT);
+    press(&mut pong, Buttons::DOWN);
+    press(&mut pong, Buttons::RIGHT);
+    assert_snapshot("settings_menu", pong.display());
+}
+
+#[test]
+fn closing_a_menu_leaves_the_playfield_intact() {
+    let mut buffer = screen_buffer();
+    let mut pong = start_match(&mut buffer);
+    press(&mut pong, Buttons::START);
+    let paused = to_ppm(pong.display());
+
+    press(&mut pong, Buttons::SELECT);
+    assert_ne!(paused, to_ppm(pong.display()));
+    press(&mut pong, Buttons::CIRCLE);
+    assert!(paused == to_ppm(pong.display()), "the paused frame changed after opening and closing the menu");
+}
+
+#[test]
+fn paddle_moving_up() {
+    let mut paddle = PaddleBuilder::new()
+        .set_default_dimensions()
+        .set_speed(PaddleMovementSpeed::Normal)
+        .set_x(10)
+        .set_y(SCREEN_HEIGHT as i32/2)
+        .build();
+    for _ in 0..3 {
+        paddle.move_up();
+    }
+
+    let mut buffer = screen_buffer();
+    let mut display = MemoryDisplay::new(&mut buffer);
+    paddle.draw(&mut display, 0.5); // halfway through the last move
+    assert_eq!(display.pixel(Point::new(15, SCREEN_HEIGHT as i32/2 - 10)), Some(Rgb888::WHITE));
+    assert_eq!(display.pixel(Point::new(15, SCREEN_HEIGHT as i32/2 + 40)), Some(Rgb888::BLACK));
+    assert_snapshot("paddle_moving_up", &display);
+}
+
+#[test]
+fn ball_moving() {
+    let mut rng = ChaCha20Rng::seed_from_u64(SEED);
+    let mut ball = Ball::new(SCREEN_WIDTH as i32/2, SCREEN_HEIGHT as i32/2, 10, 0.0, &mut rng);
+    ball.start_step();
+    ball.move_ball(Vec2::new(40.0, -20.0));
+
+    let mut buffer = screen_buffer();
+    let mut display = MemoryDisplay::new(&mut buffer);
+    ball.draw(&mut display, 1.0);
+    assert_snapshot("ball_moving", &display);
+}
+
+#[test]
+fn menu_with_every_kind_of_item() {
+    let mut menu = MenuBuilder::new()
+        .set_title("Snapshot")
+        .set_items([
+            MenuItem::button("Button", 0),
+            MenuItem::toggle("Toggle", 1, true),
+            MenuItem::picker("Picker", &[("One", 2), ("Two", 4)], 1),
+            MenuItem::slider("Slider", 3, 0, 10, 1, 7),
+        ])
+        .set_item_font(Font12x16)
+        .set_title_font(Font12x16)
+        .set_position(Point::new(SCREEN_WIDTH as i32/2 - 150, 20))
+        .set_size(Size::new(300, 120))
+        .build();
+    menu.move_down();
+
+    let mut buffer = screen_buffer();
+    let mut display = MemoryDisplay::new(&mut buffer);
+    menu.draw(&mut display);
+    assert_snapshot("menu_with_every_kind_of_item", &display);
+}